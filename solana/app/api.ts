@@ -1,8 +1,34 @@
 import { Program } from "@project-serum/anchor";
 import * as anchor from "@project-serum/anchor";
 import { Rental } from "../target/types/rental";
+import {
+  TOKEN_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccountInstruction,
+  getAssociatedTokenAddress,
+} from "@solana/spl-token";
 import web3 = anchor.web3;
 
+const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey(
+  // "metaqbxxUerdq28cj1RbAWkYQm3ybzdjb6a8bt518x1s"
+  "RTSE3BtLs2dDR482uuKMvdcGwWnbbQqUFEkxnTZC7FG" // Local deployment
+);
+const TOKEN_AUTH_RULES_PROGRAM_ID = new web3.PublicKey(
+  "auth9SigNpDKz4sJJ1DfCTuZrZNSAgh9sFD3rboVmgg"
+);
+const BUBBLEGUM_PROGRAM_ID = new web3.PublicKey(
+  "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY"
+);
+const COMPRESSION_PROGRAM_ID = new web3.PublicKey(
+  "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK"
+);
+const NOOP_PROGRAM_ID = new web3.PublicKey(
+  "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNPtsmV"
+);
+const BPF_LOADER_UPGRADEABLE_ID = new web3.PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
 export interface CollectionKey {
   symbol: string;
   authority: web3.PublicKey;
@@ -22,6 +48,42 @@ export type CollectionApp = Awaited<
   ReturnType<typeof typeProg.account.collectionApp.fetch>
 >;
 export type NFT = Awaited<ReturnType<typeof typeProg.account.nft.fetch>>;
+export type RentalPolicy = Collection["policy"];
+export type Bookings = Awaited<
+  ReturnType<typeof typeProg.account.bookings.fetch>
+>;
+export type RentalAuction = Awaited<
+  ReturnType<typeof typeProg.account.rentalAuction.fetch>
+>;
+export type AccessPolicy = NFT["access"];
+export type RentalRecord = Awaited<
+  ReturnType<typeof typeProg.account.rentalRecord.fetch>
+>;
+export type MintGate = Awaited<
+  ReturnType<typeof typeProg.account.mintGate.fetch>
+>;
+export type RentalOffer = Awaited<
+  ReturnType<typeof typeProg.account.rentalOffer.fetch>
+>;
+
+export type CompressedNFT = Awaited<
+  ReturnType<typeof typeProg.account.compressedNft.fetch>
+>;
+
+// Leaf of a compressed nft, as of the current tree root
+export interface CompressedLeaf {
+  root: number[];
+  dataHash: number[];
+  creatorHash: number[];
+  nonce: anchor.BN;
+  index: number;
+}
+
+// Merkle proofs for the nft & collection access policies
+export interface AccessProofs {
+  nft?: number[][];
+  collection?: number[][];
+}
 
 export function getAPI(program: Program<Rental>) {
   // Retreive types
@@ -56,6 +118,421 @@ export function getAPI(program: Program<Rental>) {
     return { pda, bump };
   }
 
+  async function getBookingsPda(
+    mint: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("bookings"), mint.toBytes()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getAuctionPda(
+    mint: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("auction"), mint.toBytes()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getOfferPda(
+    mint: web3.PublicKey,
+    renter: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("offer"), mint.toBytes(), renter.toBytes()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getAccessEntryPda(
+    scope: web3.PublicKey,
+    wallet: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("access"), scope.toBytes(), wallet.toBytes()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  // Account checked against an access policy of scope
+  async function getAccessAccount(
+    access: AccessPolicy,
+    scope: web3.PublicKey,
+    renter: web3.PublicKey
+  ): Promise<web3.PublicKey> {
+    if (access.allowlist || access.denylist)
+      return (await getAccessEntryPda(scope, renter)).pda;
+    if (access.tokenHolder)
+      return getAssociatedTokenAddress(access.tokenHolder.mint, renter);
+    return web3.SystemProgram.programId;
+  }
+
+  // Remaining accounts paying `to` in an spl payment mint, `tokenProgram` being
+  // the Token or Token-2022 program owning the mint
+  async function getPaymentAccounts(
+    paymentMint: web3.PublicKey,
+    from: web3.PublicKey,
+    to: web3.PublicKey,
+    tokenProgram: web3.PublicKey = TOKEN_PROGRAM_ID
+  ): Promise<web3.AccountMeta[]> {
+    return [
+      {
+        pubkey: await getAssociatedTokenAddress(
+          paymentMint,
+          from,
+          false,
+          tokenProgram
+        ),
+        isWritable: true,
+        isSigner: false,
+      },
+      {
+        pubkey: await getAssociatedTokenAddress(
+          paymentMint,
+          to,
+          false,
+          tokenProgram
+        ),
+        isWritable: true,
+        isSigner: false,
+      },
+      { pubkey: tokenProgram, isWritable: false, isSigner: false },
+      { pubkey: paymentMint, isWritable: false, isSigner: false },
+    ];
+  }
+
+  // Escrow accounts of an spl payment mint: the token accounts of `holders`,
+  // and the vault of `escrow` with the token program & payment mint, plus the
+  // instruction creating the vault when missing. None for lamport payments
+  async function getVaultAccounts(
+    escrow: web3.PublicKey,
+    paymentMint: web3.PublicKey,
+    holders: web3.PublicKey[] = []
+  ) {
+    const vaultAccounts = {
+      tokenAccounts: [] as web3.AccountMeta[],
+      vaultAccounts: [] as web3.AccountMeta[],
+      preInstructions: [] as web3.TransactionInstruction[],
+    };
+    if (paymentMint.equals(web3.PublicKey.default)) return vaultAccounts;
+    const connection = program.provider.connection;
+    const tokenProgram = (await connection.getAccountInfo(paymentMint)).owner;
+    for (const holder of holders) {
+      vaultAccounts.tokenAccounts.push({
+        pubkey: await getAssociatedTokenAddress(
+          paymentMint,
+          holder,
+          false,
+          tokenProgram
+        ),
+        isWritable: true,
+        isSigner: false,
+      });
+    }
+    const vault = await getAssociatedTokenAddress(
+      paymentMint,
+      escrow,
+      true,
+      tokenProgram
+    );
+    vaultAccounts.vaultAccounts.push(
+      { pubkey: vault, isWritable: true, isSigner: false },
+      { pubkey: tokenProgram, isWritable: false, isSigner: false },
+      { pubkey: paymentMint, isWritable: false, isSigner: false }
+    );
+    if (!(await connection.getAccountInfo(vault))) {
+      vaultAccounts.preInstructions.push(
+        createAssociatedTokenAccountInstruction(
+          (program.provider as anchor.AnchorProvider).wallet.publicKey,
+          vault,
+          escrow,
+          paymentMint,
+          tokenProgram
+        )
+      );
+    }
+    return vaultAccounts;
+  }
+
+  async function getCNFTPda(
+    assetId: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("cnft"), assetId.toBuffer()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getCNFTCustodyPda(): Promise<{
+    pda: web3.PublicKey;
+    bump: number;
+  }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("cnft_custody")],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  // Tree & programs updating the leaves of compressed nfts
+  async function getTreeAccounts(merkleTree: web3.PublicKey) {
+    const [treeAuthority] = await web3.PublicKey.findProgramAddress(
+      [merkleTree.toBuffer()],
+      BUBBLEGUM_PROGRAM_ID
+    );
+    return {
+      treeAuthority,
+      merkleTree,
+      logWrapper: NOOP_PROGRAM_ID,
+      compressionProgram: COMPRESSION_PROGRAM_ID,
+      bubblegumProgram: BUBBLEGUM_PROGRAM_ID,
+      systemProgram: web3.SystemProgram.programId,
+    };
+  }
+
+  // Leaf proof, as trailing remaining accounts
+  function proofAccounts(proof: web3.PublicKey[]): web3.AccountMeta[] {
+    return proof.map((node) => ({
+      pubkey: node,
+      isWritable: false,
+      isSigner: false,
+    }));
+  }
+
+  async function getMintGatePda(
+    collection: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("mint_gate"), collection.toBuffer()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getPassAuthorityPda(): Promise<{
+    pda: web3.PublicKey;
+    bump: number;
+  }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("pass_authority")],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getRentalPassPda(
+    passMint: web3.PublicKey
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("rental_pass"), passMint.toBytes()],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getRentalRecordPda(
+    mint: web3.PublicKey,
+    index: number | anchor.BN
+  ): Promise<{ pda: web3.PublicKey; bump: number }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("rental_record"),
+        mint.toBytes(),
+        new anchor.BN(index).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  // Record of the rental `nft` would start next
+  async function getNextRecordPda(nft: NFT) {
+    return getRentalRecordPda(nft.mint, nft.rentalCount);
+  }
+
+  // Record of the latest `nft` rental
+  async function getLatestRecordPda(nft: NFT) {
+    const count = nft.rentalCount;
+    return getRentalRecordPda(nft.mint, count.isZero() ? 0 : count.subn(1));
+  }
+
+  async function getConfigPda(): Promise<{
+    pda: web3.PublicKey;
+    bump: number;
+  }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("config")],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getDelegatePda(): Promise<{
+    pda: web3.PublicKey;
+    bump: number;
+  }> {
+    const [pda, bump] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("delegate")],
+      program.programId
+    );
+    return { pda, bump };
+  }
+
+  async function getEditionPda(mint: web3.PublicKey): Promise<web3.PublicKey> {
+    const [pda] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("metadata"),
+        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+        mint.toBuffer(),
+        Buffer.from("edition"),
+      ],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    return pda;
+  }
+
+  async function getMetadataPda(mint: web3.PublicKey): Promise<web3.PublicKey> {
+    const [pda] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("metadata"),
+        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+        mint.toBuffer(),
+      ],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    return pda;
+  }
+
+  async function getTokenRecordPda(
+    mint: web3.PublicKey,
+    tokenAccount: web3.PublicKey
+  ): Promise<web3.PublicKey> {
+    const [pda] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("metadata"),
+        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+        mint.toBuffer(),
+        Buffer.from("token_record"),
+        tokenAccount.toBuffer(),
+      ],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    return pda;
+  }
+
+  // Accounts locking a programmable nft, ignored for other nfts. `ruleSet` is
+  // the token auth rules set of the nft, if any
+  async function programmableAccounts(
+    mint: web3.PublicKey,
+    tokenAccount: web3.PublicKey,
+    ruleSet?: web3.PublicKey
+  ): Promise<web3.AccountMeta[]> {
+    return [
+      {
+        pubkey: await getTokenRecordPda(mint, tokenAccount),
+        isWritable: true,
+        isSigner: false,
+      },
+      {
+        pubkey: web3.SystemProgram.programId,
+        isWritable: false,
+        isSigner: false,
+      },
+      {
+        pubkey: web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+        isWritable: false,
+        isSigner: false,
+      },
+      {
+        pubkey: ruleSet ? TOKEN_AUTH_RULES_PROGRAM_ID : TOKEN_METADATA_PROGRAM_ID,
+        isWritable: false,
+        isSigner: false,
+      },
+      {
+        pubkey: ruleSet ?? TOKEN_METADATA_PROGRAM_ID,
+        isWritable: false,
+        isSigner: false,
+      },
+    ];
+  }
+
+  // Accounts freezing a delegated nft, appended to the remaining accounts of
+  // the instructions starting a rental
+  async function delegationAccounts(
+    nft: NFT,
+    ruleSet?: web3.PublicKey
+  ): Promise<web3.AccountMeta[]> {
+    if (nft.tokenAccount.equals(web3.PublicKey.default)) return [];
+    const delegate = await getDelegatePda();
+    return [
+      { pubkey: nft.tokenAccount, isWritable: true, isSigner: false },
+      {
+        pubkey: await getEditionPda(nft.mint),
+        isWritable: false,
+        isSigner: false,
+      },
+      { pubkey: nft.mint, isWritable: false, isSigner: false },
+      { pubkey: delegate.pda, isWritable: false, isSigner: false },
+      { pubkey: TOKEN_PROGRAM_ID, isWritable: false, isSigner: false },
+      {
+        pubkey: TOKEN_METADATA_PROGRAM_ID,
+        isWritable: false,
+        isSigner: false,
+      },
+      {
+        pubkey: await getMetadataPda(nft.mint),
+        isWritable: true,
+        isSigner: false,
+      },
+      ...(await programmableAccounts(nft.mint, nft.tokenAccount, ruleSet)),
+    ];
+  }
+
+  async function fetchConfig() {
+    const { pda } = await getConfigPda();
+    return program.account.config.fetch(pda);
+  }
+
+  // `admin` must be the program upgrade authority
+  async function initConfig(admin: web3.PublicKey, signers: web3.Keypair[] = []) {
+    const [programData] = await web3.PublicKey.findProgramAddress(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_ID
+    );
+    await program.methods
+      .initConfig()
+      .accounts({
+        config: (await getConfigPda()).pda,
+        admin: admin,
+        programData,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function setPaused(
+    paused: boolean,
+    admin: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    await program.methods
+      .setPaused(paused)
+      .accounts({
+        config: (await getConfigPda()).pda,
+        admin: admin,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
   async function fetchCollection(
     key: CollectionKey | web3.PublicKey
   ): Promise<Collection> {
@@ -107,77 +584,305 @@ export function getAPI(program: Program<Rental>) {
       .rpc();
   }
 
-  async function fetchCollectionApp(pubkey: web3.PublicKey) {
-    return program.account.collectionApp.fetch(pubkey);
+  async function updateCollectionPolicy(
+    key: CollectionKey,
+    policy: RentalPolicy
+  ) {
+    const { pda } = await getCollectionPda(key);
+    await program.methods
+      .updateCollectionPolicy(key.symbol, policy)
+      .accounts({
+        authority: key.authority,
+        collection: pda,
+      })
+      .rpc();
   }
 
-  async function fetchCollectionApps(
-    key: CollectionKey
-  ): Promise<Keyed<CollectionApp>[]> {
-    const collectionPda = await getCollectionPda(key);
-    return program.account.collectionApp.all([
-      {
-        memcmp: {
-          offset: 8, // Discriminator.
-          bytes: collectionPda.pda.toBase58(),
-        },
-      },
-    ]);
+  // Swaps the metadata uri of rented nfts of the collection to a rented variant
+  async function setRentedMetadata(key: CollectionKey, enabled: boolean) {
+    const { pda } = await getCollectionPda(key);
+    await program.methods
+      .setRentedMetadata(key.symbol, enabled)
+      .accounts({
+        collection: pda,
+        authority: key.authority,
+      })
+      .rpc();
   }
 
-  async function createCollectionApp(
-    key: CollectionKey,
-    collectionApp: CollectionApp
-  ): Promise<web3.PublicKey> {
-    const collectionPda = await getCollectionPda(key);
-    const collectionAppKey = anchor.web3.Keypair.generate();
+  async function closeCollection(key: CollectionKey) {
+    const { pda } = await getCollectionPda(key);
     await program.methods
-      .createCollectionApp(
-        key.symbol,
-        collectionApp.name,
-        collectionApp.imageUrl,
-        collectionApp.appUrl
-      )
+      .closeCollection(key.symbol)
       .accounts({
         authority: key.authority,
-        collection: collectionPda.pda,
-        collectionApp: collectionAppKey.publicKey,
-        systemProgram: web3.SystemProgram.programId,
+        collection: pda,
       })
-      .signers([collectionAppKey])
       .rpc();
-    return collectionAppKey.publicKey;
   }
 
-  async function updateCollectionApp(
-    key: CollectionKey,
-    collectionAppPubkey: web3.PublicKey,
-    collectionApp: CollectionApp
-  ) {
+  // Mints the Metaplex collection nft, authority of the collection pda
+  async function createCollectionMint(key: CollectionKey, uri: string) {
+    const { pda } = await getCollectionPda(key);
+    const mint = web3.Keypair.generate();
     await program.methods
-      .updateCollectionApp(
-        collectionApp.name,
-        collectionApp.imageUrl,
-        collectionApp.appUrl
-      )
+      .createCollectionMint(key.symbol, uri)
       .accounts({
+        collection: pda,
+        mint: mint.publicKey,
+        tokenAccount: await getAssociatedTokenAddress(
+          mint.publicKey,
+          key.authority
+        ),
+        metadata: await getMetadataPda(mint.publicKey),
+        masterEdition: await getEditionPda(mint.publicKey),
         authority: key.authority,
-        collectionApp: collectionAppPubkey,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+        config: (await getConfigPda()).pda,
       })
+      .signers([mint])
       .rpc();
+    return mint.publicKey;
   }
 
-  async function removeCollectionApp(
-    key: CollectionKey,
-    collectionAppPubkey: web3.PublicKey
-  ) {
-    const collectionPda = await getCollectionPda(key);
+  // Mints an nft in the collection, listed with the collection default terms
+  async function mintAndList(key: CollectionKey, uri: string, title: string) {
+    const { pda } = await getCollectionPda(key);
+    const collection = await program.account.collection.fetch(pda);
+    const mint = web3.Keypair.generate();
     await program.methods
-      .removeCollectionApp(key.symbol)
+      .mintAndList(key.symbol, uri, title)
       .accounts({
+        collection: pda,
+        mint: mint.publicKey,
+        tokenAccount: await getAssociatedTokenAddress(
+          mint.publicKey,
+          key.authority
+        ),
+        nft: (await getNFTPda(mint.publicKey)).pda,
+        metadata: await getMetadataPda(mint.publicKey),
+        masterEdition: await getEditionPda(mint.publicKey),
+        collectionMint: collection.collectionMint,
+        collectionMetadata: await getMetadataPda(collection.collectionMint),
+        collectionMasterEdition: await getEditionPda(collection.collectionMint),
         authority: key.authority,
-        collectionApp: collectionAppPubkey,
-        collection: collectionPda.pda,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+        config: (await getConfigPda()).pda,
+      })
+      .signers([mint])
+      .rpc();
+    return mint.publicKey;
+  }
+
+  // Updates an nft minted in the collection, unset fields are kept.
+  // isMutable false locks the metadata for good
+  async function updateNFTMetadata(
+    key: CollectionKey,
+    mint: web3.PublicKey,
+    update: {
+      name?: string;
+      uri?: string;
+      creators?: { address: web3.PublicKey; share: number }[];
+      isMutable?: boolean;
+    }
+  ) {
+    const { pda } = await getCollectionPda(key);
+    await program.methods
+      .updateNftMetadata(
+        key.symbol,
+        mint,
+        update.name ?? null,
+        update.uri ?? null,
+        update.creators ?? null,
+        update.isMutable ?? null
+      )
+      .accounts({
+        collection: pda,
+        metadata: await getMetadataPda(mint),
+        authority: key.authority,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  async function fetchMintGate(key: CollectionKey) {
+    const collectionPda = await getCollectionPda(key);
+    const { pda } = await getMintGatePda(collectionPda.pda);
+    return program.account.mintGate.fetch(pda);
+  }
+
+  async function initMintGate(
+    key: CollectionKey,
+    gate: Pick<MintGate, "price" | "paymentMint" | "access" | "maxSupply">
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    await program.methods
+      .initMintGate(
+        key.symbol,
+        gate.price,
+        gate.paymentMint,
+        gate.access,
+        gate.maxSupply
+      )
+      .accounts({
+        mintGate: (await getMintGatePda(collectionPda.pda)).pda,
+        collection: collectionPda.pda,
+        authority: key.authority,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  async function updateMintGate(
+    key: CollectionKey,
+    gate: Pick<MintGate, "price" | "paymentMint" | "access" | "maxSupply">
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    await program.methods
+      .updateMintGate(
+        key.symbol,
+        gate.price,
+        gate.paymentMint,
+        gate.access,
+        gate.maxSupply
+      )
+      .accounts({
+        mintGate: (await getMintGatePda(collectionPda.pda)).pda,
+        collection: collectionPda.pda,
+        authority: key.authority,
+      })
+      .rpc();
+  }
+
+  // Mints an nft in the collection through its mint gate, paid by the minter
+  async function gatedMint(
+    key: CollectionKey,
+    uri: string,
+    title: string,
+    minter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    // Minter token account, authority token account, token program & payment mint for spl payments
+    paymentAccounts: web3.AccountMeta[] = [],
+    proof: number[][] = []
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    const collection = await program.account.collection.fetch(
+      collectionPda.pda
+    );
+    const mintGatePda = await getMintGatePda(collectionPda.pda);
+    const mintGate = await program.account.mintGate.fetch(mintGatePda.pda);
+    const mint = web3.Keypair.generate();
+    await program.methods
+      .gatedMint(key.symbol, uri, title, proof)
+      .accounts({
+        collection: collectionPda.pda,
+        mintGate: mintGatePda.pda,
+        mint: mint.publicKey,
+        tokenAccount: await getAssociatedTokenAddress(mint.publicKey, minter),
+        metadata: await getMetadataPda(mint.publicKey),
+        masterEdition: await getEditionPda(mint.publicKey),
+        collectionMint: collection.collectionMint,
+        collectionMetadata: await getMetadataPda(collection.collectionMint),
+        collectionMasterEdition: await getEditionPda(collection.collectionMint),
+        authority: key.authority,
+        minterAccess: await getAccessAccount(
+          mintGate.access,
+          collectionPda.pda,
+          minter
+        ),
+        minter: minter,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+        rent: web3.SYSVAR_RENT_PUBKEY,
+        config: (await getConfigPda()).pda,
+      })
+      .remainingAccounts(paymentAccounts)
+      .signers([mint, ...signers])
+      .rpc();
+    return mint.publicKey;
+  }
+
+  async function fetchCollectionApp(pubkey: web3.PublicKey) {
+    return program.account.collectionApp.fetch(pubkey);
+  }
+
+  async function fetchCollectionApps(
+    key: CollectionKey
+  ): Promise<Keyed<CollectionApp>[]> {
+    const collectionPda = await getCollectionPda(key);
+    return program.account.collectionApp.all([
+      {
+        memcmp: {
+          offset: 8, // Discriminator.
+          bytes: collectionPda.pda.toBase58(),
+        },
+      },
+    ]);
+  }
+
+  async function createCollectionApp(
+    key: CollectionKey,
+    collectionApp: CollectionApp
+  ): Promise<web3.PublicKey> {
+    const collectionPda = await getCollectionPda(key);
+    const collectionAppKey = anchor.web3.Keypair.generate();
+    await program.methods
+      .createCollectionApp(
+        key.symbol,
+        collectionApp.name,
+        collectionApp.imageUrl,
+        collectionApp.appUrl
+      )
+      .accounts({
+        authority: key.authority,
+        collection: collectionPda.pda,
+        collectionApp: collectionAppKey.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers([collectionAppKey])
+      .rpc();
+    return collectionAppKey.publicKey;
+  }
+
+  async function updateCollectionApp(
+    key: CollectionKey,
+    collectionAppPubkey: web3.PublicKey,
+    collectionApp: CollectionApp
+  ) {
+    await program.methods
+      .updateCollectionApp(
+        collectionApp.name,
+        collectionApp.imageUrl,
+        collectionApp.appUrl
+      )
+      .accounts({
+        authority: key.authority,
+        collectionApp: collectionAppPubkey,
+      })
+      .rpc();
+  }
+
+  async function removeCollectionApp(
+    key: CollectionKey,
+    collectionAppPubkey: web3.PublicKey
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    await program.methods
+      .removeCollectionApp(key.symbol)
+      .accounts({
+        authority: key.authority,
+        collectionApp: collectionAppPubkey,
+        collection: collectionPda.pda,
       })
       .rpc();
   }
@@ -213,10 +918,13 @@ export function getAPI(program: Program<Rental>) {
         collection,
         nft.rentalMaxDays,
         nft.rentalPrice,
-        nft.rentalEnabled
+        nft.rentalEnabled,
+        nft.paymentMint
       )
       .accounts({
+        config: (await getConfigPda()).pda,
         nft: nftPda.pda,
+        collection,
         owner: owner,
         systemProgram: web3.SystemProgram.programId,
       })
@@ -232,11 +940,167 @@ export function getAPI(program: Program<Rental>) {
   ) {
     const nftPda = await getNFTPda(mint);
     await program.methods
-      .updateNft(mint, nft.rentalMaxDays, nft.rentalPrice, nft.rentalEnabled)
+      .updateNft(
+        mint,
+        nft.rentalMaxDays,
+        nft.rentalPrice,
+        nft.rentalEnabled,
+        nft.paymentMint
+      )
+      .accounts({
+        nft: nftPda.pda,
+        collection: nft.collection,
+        bookings: (await getBookingsPda(mint)).pda,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function deleteNFT(
+    mint: web3.PublicKey,
+    collection: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const bookingsPda = await getBookingsPda(mint);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    await program.methods
+      .deleteNft(mint)
       .accounts({
         nft: nftPda.pda,
+        collection,
+        bookings: bookingsPda.pda,
+        auction: (await getAuctionPda(mint)).pda,
+        cnft: (await getCNFTPda(mint)).pda,
+        record: (await getLatestRecordPda(nft)).pda,
+        metadata: await getMetadataPda(mint),
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function fetchCNFT(assetId: web3.PublicKey) {
+    const { pda } = await getCNFTPda(assetId);
+    return program.account.compressedNft.fetch(pda);
+  }
+
+  // Lists a compressed nft, its leaf moving to the program custody
+  async function createCNFT(
+    assetId: web3.PublicKey,
+    merkleTree: web3.PublicKey,
+    leaf: CompressedLeaf,
+    proof: web3.PublicKey[],
+    collection: web3.PublicKey,
+    nft: NFT,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    await program.methods
+      .createCnft(
+        assetId,
+        leaf,
+        nft.rentalMaxDays,
+        nft.rentalPrice,
+        nft.rentalEnabled,
+        nft.paymentMint
+      )
+      .accounts({
+        config: (await getConfigPda()).pda,
+        nft: (await getNFTPda(assetId)).pda,
+        cnft: (await getCNFTPda(assetId)).pda,
+        collection,
+        custody: (await getCNFTCustodyPda()).pda,
         owner: owner,
+        tree: await getTreeAccounts(merkleTree),
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts(proofAccounts(proof))
+      .signers(signers)
+      .rpc();
+  }
+
+  // Rents a compressed nft, its leaf staying in the program custody
+  async function rentCNFT(
+    assetId: web3.PublicKey,
+    days: number,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    // Renter token account, owner token account, token program & payment mint for spl payments
+    paymentAccounts: web3.AccountMeta[] = [],
+    proofs: AccessProofs = {}
+  ) {
+    const nftPda = await getNFTPda(assetId);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    const collection = await program.account.collection.fetch(nft.collection);
+    await program.methods
+      .rentCnft(assetId, days, proofs.nft ?? [], proofs.collection ?? [])
+      .accounts({
+        config: (await getConfigPda()).pda,
+        nft: nftPda.pda,
+        cnft: (await getCNFTPda(assetId)).pda,
+        collection: nft.collection,
+        bookings: (await getBookingsPda(assetId)).pda,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, renter),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          nft.collection,
+          renter
+        ),
+        record: (await getNextRecordPda(nft)).pda,
+        metadata: await getMetadataPda(nft.mint),
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        owner: nft.owner,
+        renter: renter,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts(paymentAccounts)
+      .signers(signers)
+      .rpc();
+  }
+
+  // Releases an expired compressed nft rental
+  async function releaseCNFT(assetId: web3.PublicKey) {
+    const nftPda = await getNFTPda(assetId);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    await program.methods
+      .releaseCnft(assetId)
+      .accounts({
+        nft: nftPda.pda,
+        cnft: (await getCNFTPda(assetId)).pda,
+        collection: nft.collection,
+        record: (await getLatestRecordPda(nft)).pda,
+      })
+      .rpc();
+  }
+
+  // Delists a compressed nft, its leaf going back to the owner
+  async function deleteCNFT(
+    assetId: web3.PublicKey,
+    root: number[],
+    proof: web3.PublicKey[],
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(assetId);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    const cnft = await fetchCNFT(assetId);
+    await program.methods
+      .deleteCnft(assetId, root)
+      .accounts({
+        nft: nftPda.pda,
+        cnft: (await getCNFTPda(assetId)).pda,
+        collection: nft.collection,
+        bookings: (await getBookingsPda(assetId)).pda,
+        auction: (await getAuctionPda(assetId)).pda,
+        record: (await getLatestRecordPda(nft)).pda,
+        custody: (await getCNFTCustodyPda()).pda,
+        owner: nft.owner,
+        tree: await getTreeAccounts(cnft.merkleTree),
       })
+      .remainingAccounts(proofAccounts(proof))
       .signers(signers)
       .rpc();
   }
@@ -247,33 +1111,842 @@ export function getAPI(program: Program<Rental>) {
     days: number,
     owner: web3.PublicKey,
     renter: web3.PublicKey,
-    signers: web3.Keypair[] = []
+    signers: web3.Keypair[] = [],
+    // Renter token account, owner token account, token program & payment mint for spl payments,
+    // the bookings vault being appended
+    paymentAccounts: web3.AccountMeta[] = [],
+    proofs: AccessProofs = {},
+    // Mints a rental pass in the same transaction when set
+    passUri?: string
   ) {
     const nftPda = await getNFTPda(mint);
     const collectionPda = await getCollectionPda(key);
+    const bookingsPda = await getBookingsPda(mint);
     // Retreive nft
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    const recordPda = await getNextRecordPda(nft);
+    const pass = passUri ? await rentalPassAccounts(renter) : null;
+    const collection = await program.account.collection.fetch(
+      collectionPda.pda
+    );
+    // Pays the owner the bookings escrowed in the vault, used or not
+    const bookingsVault = paymentAccounts.length
+      ? [
+          {
+            pubkey: await getAssociatedTokenAddress(
+              nft.paymentMint,
+              bookingsPda.pda,
+              true,
+              paymentAccounts[2].pubkey
+            ),
+            isWritable: true,
+            isSigner: false,
+          },
+        ]
+      : [];
     await program.methods
-      .rentNft(mint, key.symbol, key.authority, days)
+      .rentNft(
+        mint,
+        key.symbol,
+        key.authority,
+        days,
+        proofs.nft ?? [],
+        proofs.collection ?? [],
+        passUri ?? null
+      )
       .accounts({
+        config: (await getConfigPda()).pda,
         collection: collectionPda.pda,
         nft: nftPda.pda,
+        bookings: bookingsPda.pda,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, renter),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          collectionPda.pda,
+          renter
+        ),
+        record: recordPda.pda,
+        metadata: await getMetadataPda(mint),
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
         owner: owner,
         renter: renter,
         systemProgram: web3.SystemProgram.programId,
       })
+      .remainingAccounts([
+        ...paymentAccounts,
+        ...bookingsVault,
+        ...(await delegationAccounts(nft)),
+        ...(pass?.accounts ?? []),
+      ])
+      .signers(pass ? [...signers, pass.passMint] : signers)
+      .rpc();
+    return pass?.passMint.publicKey;
+  }
+
+  async function releaseRental(mint: web3.PublicKey) {
+    const nftPda = await getNFTPda(mint);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    await program.methods
+      .releaseRental(mint)
+      .accounts({
+        nft: nftPda.pda,
+        collection: nft.collection,
+        record: (await getLatestRecordPda(nft)).pda,
+        metadata: await getMetadataPda(mint),
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  async function fetchRentalRecord(mint: web3.PublicKey, index: number) {
+    const { pda } = await getRentalRecordPda(mint, index);
+    return program.account.rentalRecord.fetch(pda);
+  }
+
+  async function fetchRentalRecords(mint: web3.PublicKey) {
+    return program.account.rentalRecord.all([
+      {
+        memcmp: {
+          offset: 8, // Discriminator
+          bytes: mint.toBase58(),
+        },
+      },
+    ]);
+  }
+
+  async function closeRentalRecord(
+    mint: web3.PublicKey,
+    index: number,
+    payer: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const { pda } = await getRentalRecordPda(mint, index);
+    await program.methods
+      .closeRentalRecord()
+      .accounts({ record: pda, payer: payer })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function delegateNFT(
+    mint: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    ruleSet?: web3.PublicKey
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const delegate = await getDelegatePda();
+    const tokenAccount = await getAssociatedTokenAddress(mint, owner);
+    await program.methods
+      .delegateNft(mint)
+      .accounts({
+        nft: nftPda.pda,
+        tokenAccount: tokenAccount,
+        delegateAuthority: delegate.pda,
+        edition: await getEditionPda(mint),
+        nftMint: mint,
+        metadata: await getMetadataPda(mint),
+        owner: owner,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+      })
+      .remainingAccounts(await programmableAccounts(mint, tokenAccount, ruleSet))
       .signers(signers)
       .rpc();
   }
 
+  async function freezeRentedNFT(
+    mint: web3.PublicKey,
+    ruleSet?: web3.PublicKey
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const delegate = await getDelegatePda();
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    await program.methods
+      .freezeRentedNft(mint)
+      .accounts({
+        nft: nftPda.pda,
+        tokenAccount: nft.tokenAccount,
+        edition: await getEditionPda(mint),
+        nftMint: mint,
+        delegateAuthority: delegate.pda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        metadata: await getMetadataPda(mint),
+      })
+      .remainingAccounts(
+        await programmableAccounts(mint, nft.tokenAccount, ruleSet)
+      )
+      .rpc();
+  }
+
+  async function endRental(
+    mint: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    ruleSet?: web3.PublicKey
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const delegate = await getDelegatePda();
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    await program.methods
+      .endRental(mint)
+      .accounts({
+        nft: nftPda.pda,
+        tokenAccount: nft.tokenAccount,
+        edition: await getEditionPda(mint),
+        nftMint: mint,
+        delegateAuthority: delegate.pda,
+        owner: owner,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        metadata: await getMetadataPda(mint),
+      })
+      .remainingAccounts(
+        await programmableAccounts(mint, nft.tokenAccount, ruleSet)
+      )
+      .signers(signers)
+      .rpc();
+  }
+
+  // Rental pass accounts of rentNFT, minting to a fresh pass mint
+  async function rentalPassAccounts(renter: web3.PublicKey) {
+    const passMint = web3.Keypair.generate();
+    const accounts = [
+      (await getRentalPassPda(passMint.publicKey)).pda,
+      passMint.publicKey,
+      await getAssociatedTokenAddress(passMint.publicKey, renter),
+      await getMetadataPda(passMint.publicKey),
+      (await getPassAuthorityPda()).pda,
+      TOKEN_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID,
+      web3.SYSVAR_RENT_PUBKEY,
+    ].map((pubkey, index) => ({
+      pubkey,
+      isSigner: index === 1,
+      isWritable: index < 4,
+    }));
+    return { accounts, passMint };
+  }
+
+  async function fetchRentalPass(passMint: web3.PublicKey) {
+    const { pda } = await getRentalPassPda(passMint);
+    return program.account.rentalPass.fetch(pda);
+  }
+
+  async function burnExpiredPass(passMint: web3.PublicKey) {
+    const rentalPassPda = await getRentalPassPda(passMint);
+    const passAuthority = await getPassAuthorityPda();
+    const rentalPass = await program.account.rentalPass.fetch(
+      rentalPassPda.pda
+    );
+    await program.methods
+      .burnExpiredPass()
+      .accounts({
+        rentalPass: rentalPassPda.pda,
+        passMint,
+        passTokenAccount: await getAssociatedTokenAddress(
+          passMint,
+          rentalPass.renter
+        ),
+        renter: rentalPass.renter,
+        passAuthority: passAuthority.pda,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+  }
+
+  async function rentBundle(
+    nfts: NFT[],
+    days: number,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    // Renter token account, owner token account, token program & payment mint for spl payments
+    paymentAccounts: web3.AccountMeta[] = [],
+    proofs: AccessProofs[] = []
+  ) {
+    const bundleAccounts: web3.AccountMeta[] = [];
+    const bundleProofs: number[][][] = [];
+    for (const [k, nft] of nfts.entries()) {
+      const nftPda = await getNFTPda(nft.mint);
+      const bookingsPda = await getBookingsPda(nft.mint);
+      const collection = await program.account.collection.fetch(
+        nft.collection
+      );
+      const nftAccess = await getAccessAccount(nft.access, nftPda.pda, renter);
+      const recordPda = await getNextRecordPda(nft);
+      const collectionAccess = await getAccessAccount(
+        collection.access,
+        nft.collection,
+        renter
+      );
+      bundleAccounts.push(
+        { pubkey: nftPda.pda, isSigner: false, isWritable: true },
+        { pubkey: nft.collection, isSigner: false, isWritable: true },
+        { pubkey: bookingsPda.pda, isSigner: false, isWritable: false },
+        { pubkey: nftAccess, isSigner: false, isWritable: false },
+        { pubkey: collectionAccess, isSigner: false, isWritable: false },
+        { pubkey: recordPda.pda, isSigner: false, isWritable: true },
+        {
+          pubkey: await getMetadataPda(nft.mint),
+          isSigner: false,
+          isWritable: true,
+        }
+      );
+      bundleProofs.push(proofs[k]?.nft ?? [], proofs[k]?.collection ?? []);
+    }
+    await program.methods
+      .rentBundle(nfts.length, days, bundleProofs)
+      .accounts({
+        config: (await getConfigPda()).pda,
+        owner: nfts[0].owner,
+        renter: renter,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        ...bundleAccounts,
+        ...paymentAccounts,
+        ...(await Promise.all(nfts.map((nft) => delegationAccounts(nft)))).flat(),
+      ])
+      .signers(signers)
+      .rpc();
+  }
+
+  async function fetchBookings(mint: web3.PublicKey) {
+    const { pda } = await getBookingsPda(mint);
+    return program.account.bookings.fetch(pda);
+  }
+
+  async function initBookings(
+    mint: web3.PublicKey,
+    cancelNoticeS: number,
+    cancelRefundPercent: number,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const bookingsPda = await getBookingsPda(mint);
+    await program.methods
+      .initBookings(mint, new anchor.BN(cancelNoticeS), cancelRefundPercent)
+      .accounts({
+        bookings: bookingsPda.pda,
+        nft: nftPda.pda,
+        owner: owner,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function updateBookings(
+    mint: web3.PublicKey,
+    cancelNoticeS: number,
+    cancelRefundPercent: number,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const bookingsPda = await getBookingsPda(mint);
+    await program.methods
+      .updateBookings(new anchor.BN(cancelNoticeS), cancelRefundPercent)
+      .accounts({
+        bookings: bookingsPda.pda,
+        nft: nftPda.pda,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function closeBookings(
+    mint: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const bookingsPda = await getBookingsPda(mint);
+    await program.methods
+      .closeBookings()
+      .accounts({
+        bookings: bookingsPda.pda,
+        nft: nftPda.pda,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function bookNFT(
+    nft: NFT,
+    start: number,
+    days: number,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    proofs: AccessProofs = {}
+  ) {
+    const nftPda = await getNFTPda(nft.mint);
+    const bookingsPda = await getBookingsPda(nft.mint);
+    const collection = await program.account.collection.fetch(nft.collection);
+    const vault = await getVaultAccounts(bookingsPda.pda, nft.paymentMint, [
+      renter,
+    ]);
+    await program.methods
+      .bookNft(
+        new anchor.BN(start),
+        days,
+        proofs.nft ?? [],
+        proofs.collection ?? []
+      )
+      .accounts({
+        config: (await getConfigPda()).pda,
+        bookings: bookingsPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, renter),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          nft.collection,
+          renter
+        ),
+        renter: renter,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([...vault.tokenAccounts, ...vault.vaultAccounts])
+      .preInstructions(vault.preInstructions)
+      .signers(signers)
+      .rpc();
+  }
+
+  async function cancelBooking(
+    nft: NFT,
+    start: number,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(nft.mint);
+    const bookingsPda = await getBookingsPda(nft.mint);
+    const vault = await getVaultAccounts(bookingsPda.pda, nft.paymentMint, [
+      renter,
+      nft.owner,
+    ]);
+    await program.methods
+      .cancelBooking(new anchor.BN(start))
+      .accounts({
+        bookings: bookingsPda.pda,
+        nft: nftPda.pda,
+        owner: nft.owner,
+        renter: renter,
+      })
+      .remainingAccounts([...vault.tokenAccounts, ...vault.vaultAccounts])
+      .signers(signers)
+      .rpc();
+  }
+
+  async function fetchAuction(mint: web3.PublicKey) {
+    const { pda } = await getAuctionPda(mint);
+    return program.account.rentalAuction.fetch(pda);
+  }
+
+  async function startRentalAuction(
+    nft: NFT,
+    days: number,
+    minBid: anchor.BN,
+    biddingEnd: number,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(nft.mint);
+    const auctionPda = await getAuctionPda(nft.mint);
+    await program.methods
+      .startRentalAuction(nft.mint, days, minBid, new anchor.BN(biddingEnd))
+      .accounts({
+        config: (await getConfigPda()).pda,
+        auction: auctionPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        owner: nft.owner,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function placeBid(
+    mint: web3.PublicKey,
+    amount: anchor.BN,
+    bidder: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    proofs: AccessProofs = {}
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const auctionPda = await getAuctionPda(mint);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    const collection = await program.account.collection.fetch(nft.collection);
+    const auction = await fetchAuction(mint);
+    const vault = await getVaultAccounts(auctionPda.pda, auction.paymentMint, [
+      bidder,
+    ]);
+    await program.methods
+      .placeBid(amount, proofs.nft ?? [], proofs.collection ?? [])
+      .accounts({
+        config: (await getConfigPda()).pda,
+        auction: auctionPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, bidder),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          nft.collection,
+          bidder
+        ),
+        bidder: bidder,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([...vault.tokenAccounts, ...vault.vaultAccounts])
+      .preInstructions(vault.preInstructions)
+      .signers(signers)
+      .rpc();
+  }
+
+  // Bidders to refund, in bid order, or their token accounts after the auction
+  // vault accounts for spl payment mints
+  async function bidderAccounts(
+    auction: RentalAuction,
+    auctionPda: web3.PublicKey,
+    exclude?: web3.PublicKey,
+    // Paid out of the vault before the bidders, the owner for settlements
+    payee?: web3.PublicKey
+  ) {
+    const bidders = auction.bids
+      .filter((bid) => !exclude || !bid.bidder.equals(exclude))
+      .map((bid) => bid.bidder);
+    if (auction.paymentMint.equals(web3.PublicKey.default))
+      return bidders.map((bidder) => ({
+        pubkey: bidder,
+        isSigner: false,
+        isWritable: true,
+      }));
+    const vault = await getVaultAccounts(auctionPda, auction.paymentMint, [
+      ...(payee ? [payee] : []),
+      ...bidders,
+    ]);
+    return [...vault.vaultAccounts, ...vault.tokenAccounts];
+  }
+
+  async function settleRentalAuction(nft: NFT) {
+    const nftPda = await getNFTPda(nft.mint);
+    const auctionPda = await getAuctionPda(nft.mint);
+    const bookingsPda = await getBookingsPda(nft.mint);
+    const auction = await fetchAuction(nft.mint);
+    const winner = auction.bids.reduce(
+      (best, bid) => (!best || bid.amount.gt(best.amount) ? bid : best),
+      null
+    );
+    await program.methods
+      .settleRentalAuction()
+      .accounts({
+        auction: auctionPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        bookings: bookingsPda.pda,
+        record: (await getNextRecordPda(nft)).pda,
+        metadata: await getMetadataPda(nft.mint),
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        owner: nft.owner,
+        payer: (program.provider as anchor.AnchorProvider).wallet.publicKey,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        ...(await bidderAccounts(
+          auction,
+          auctionPda.pda,
+          winner?.bidder,
+          nft.owner
+        )),
+        ...(await delegationAccounts(nft)),
+      ])
+      .rpc();
+  }
+
+  async function cancelRentalAuction(
+    nft: NFT,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(nft.mint);
+    const auctionPda = await getAuctionPda(nft.mint);
+    const auction = await fetchAuction(nft.mint);
+    await program.methods
+      .cancelRentalAuction()
+      .accounts({
+        auction: auctionPda.pda,
+        nft: nftPda.pda,
+        owner: nft.owner,
+      })
+      .remainingAccounts(await bidderAccounts(auction, auctionPda.pda))
+      .signers(signers)
+      .rpc();
+  }
+
+  async function fetchOffer(mint: web3.PublicKey, renter: web3.PublicKey) {
+    const { pda } = await getOfferPda(mint, renter);
+    return program.account.rentalOffer.fetch(pda);
+  }
+
+  async function fetchOffers(mint: web3.PublicKey) {
+    return program.account.rentalOffer.all([
+      {
+        memcmp: {
+          offset: 8, // Discriminator
+          bytes: mint.toBase58(),
+        },
+      },
+    ]);
+  }
+
+  async function createRentalOffer(
+    mint: web3.PublicKey,
+    price: anchor.BN,
+    days: number,
+    expiresAt: number,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    proofs: AccessProofs = {}
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const offerPda = await getOfferPda(mint, renter);
+    const nft = await program.account.nft.fetch(nftPda.pda);
+    const collection = await program.account.collection.fetch(nft.collection);
+    const vault = await getVaultAccounts(offerPda.pda, nft.paymentMint, [
+      renter,
+    ]);
+    await program.methods
+      .createRentalOffer(
+        mint,
+        price,
+        days,
+        new anchor.BN(expiresAt),
+        proofs.nft ?? [],
+        proofs.collection ?? []
+      )
+      .accounts({
+        config: (await getConfigPda()).pda,
+        offer: offerPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, renter),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          nft.collection,
+          renter
+        ),
+        renter: renter,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([...vault.tokenAccounts, ...vault.vaultAccounts])
+      .preInstructions(vault.preInstructions)
+      .signers(signers)
+      .rpc();
+  }
+
+  async function acceptRentalOffer(
+    nft: NFT,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = [],
+    proofs: AccessProofs = {}
+  ) {
+    const nftPda = await getNFTPda(nft.mint);
+    const offerPda = await getOfferPda(nft.mint, renter);
+    const bookingsPda = await getBookingsPda(nft.mint);
+    const collection = await program.account.collection.fetch(nft.collection);
+    const offer = await program.account.rentalOffer.fetch(offerPda.pda);
+    const vault = await getVaultAccounts(offerPda.pda, offer.paymentMint, [
+      nft.owner,
+    ]);
+    await program.methods
+      .acceptRentalOffer(proofs.nft ?? [], proofs.collection ?? [])
+      .accounts({
+        config: (await getConfigPda()).pda,
+        offer: offerPda.pda,
+        nft: nftPda.pda,
+        collection: nft.collection,
+        nftAccess: await getAccessAccount(nft.access, nftPda.pda, renter),
+        collectionAccess: await getAccessAccount(
+          collection.access,
+          nft.collection,
+          renter
+        ),
+        bookings: bookingsPda.pda,
+        record: (await getNextRecordPda(nft)).pda,
+        owner: nft.owner,
+        renter: renter,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        ...vault.tokenAccounts,
+        ...vault.vaultAccounts,
+        ...(await delegationAccounts(nft)),
+      ])
+      .signers(signers)
+      .rpc();
+  }
+
+  async function cancelRentalOffer(
+    mint: web3.PublicKey,
+    renter: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const offerPda = await getOfferPda(mint, renter);
+    const offer = await program.account.rentalOffer.fetch(offerPda.pda);
+    const vault = await getVaultAccounts(offerPda.pda, offer.paymentMint, [
+      renter,
+    ]);
+    await program.methods
+      .cancelRentalOffer()
+      .accounts({
+        offer: offerPda.pda,
+        renter: renter,
+      })
+      .remainingAccounts([...vault.tokenAccounts, ...vault.vaultAccounts])
+      .signers(signers)
+      .rpc();
+  }
+
+  async function setNFTAccess(
+    mint: web3.PublicKey,
+    access: AccessPolicy,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    await program.methods
+      .setNftAccess(mint, access)
+      .accounts({
+        nft: nftPda.pda,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function setCollectionAccess(key: CollectionKey, access: AccessPolicy) {
+    const { pda } = await getCollectionPda(key);
+    await program.methods
+      .setCollectionAccess(key.symbol, access)
+      .accounts({
+        collection: pda,
+        authority: key.authority,
+      })
+      .rpc();
+  }
+
+  async function addNFTAccessEntry(
+    mint: web3.PublicKey,
+    wallet: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const entryPda = await getAccessEntryPda(nftPda.pda, wallet);
+    await program.methods
+      .addNftAccessEntry(mint, wallet)
+      .accounts({
+        entry: entryPda.pda,
+        nft: nftPda.pda,
+        owner: owner,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function addCollectionAccessEntry(
+    key: CollectionKey,
+    wallet: web3.PublicKey
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    const entryPda = await getAccessEntryPda(collectionPda.pda, wallet);
+    await program.methods
+      .addCollectionAccessEntry(key.symbol, wallet)
+      .accounts({
+        entry: entryPda.pda,
+        collection: collectionPda.pda,
+        authority: key.authority,
+        systemProgram: web3.SystemProgram.programId,
+      })
+      .rpc();
+  }
+
+  async function removeNFTAccessEntry(
+    mint: web3.PublicKey,
+    wallet: web3.PublicKey,
+    owner: web3.PublicKey,
+    signers: web3.Keypair[] = []
+  ) {
+    const nftPda = await getNFTPda(mint);
+    const entryPda = await getAccessEntryPda(nftPda.pda, wallet);
+    await program.methods
+      .removeNftAccessEntry(mint)
+      .accounts({
+        entry: entryPda.pda,
+        nft: nftPda.pda,
+        owner: owner,
+      })
+      .signers(signers)
+      .rpc();
+  }
+
+  async function removeCollectionAccessEntry(
+    key: CollectionKey,
+    wallet: web3.PublicKey
+  ) {
+    const collectionPda = await getCollectionPda(key);
+    const entryPda = await getAccessEntryPda(collectionPda.pda, wallet);
+    await program.methods
+      .removeCollectionAccessEntry(key.symbol)
+      .accounts({
+        entry: entryPda.pda,
+        collection: collectionPda.pda,
+        authority: key.authority,
+      })
+      .rpc();
+  }
+
   return {
     airdrop,
     getCollectionPda,
     getNFTPda,
+    getBookingsPda,
+    getAuctionPda,
+    getOfferPda,
+    getAccessEntryPda,
+    getRentalRecordPda,
+    getConfigPda,
+    getMetadataPda,
+    getMintGatePda,
+    getPaymentAccounts,
+    getVaultAccounts,
+    getCNFTPda,
+    getCNFTCustodyPda,
+    // Config
+    fetchConfig,
+    initConfig,
+    setPaused,
     // Collection
     fetchCollection,
     fetchCollections,
     createCollection,
     updateCollection,
+    updateCollectionPolicy,
+    setRentedMetadata,
+    closeCollection,
+    // Collection mints
+    createCollectionMint,
+    mintAndList,
+    updateNFTMetadata,
+    fetchMintGate,
+    initMintGate,
+    updateMintGate,
+    gatedMint,
     // Collection apps
     fetchCollectionApp,
     fetchCollectionApps,
@@ -285,6 +1958,52 @@ export function getAPI(program: Program<Rental>) {
     fetchNFTs,
     createNFT,
     updateNFT,
+    deleteNFT,
     rentNFT,
+    rentBundle,
+    releaseRental,
+    // Compressed nfts
+    fetchCNFT,
+    createCNFT,
+    rentCNFT,
+    releaseCNFT,
+    deleteCNFT,
+    // Rental records
+    fetchRentalRecord,
+    fetchRentalRecords,
+    closeRentalRecord,
+    // Non-custodial rentals
+    delegateNFT,
+    freezeRentedNFT,
+    endRental,
+    // Rental passes
+    fetchRentalPass,
+    burnExpiredPass,
+    // Bookings
+    fetchBookings,
+    initBookings,
+    updateBookings,
+    closeBookings,
+    bookNFT,
+    cancelBooking,
+    // Auctions
+    fetchAuction,
+    startRentalAuction,
+    placeBid,
+    settleRentalAuction,
+    cancelRentalAuction,
+    // Offers
+    fetchOffer,
+    fetchOffers,
+    createRentalOffer,
+    acceptRentalOffer,
+    cancelRentalOffer,
+    // Access
+    setNFTAccess,
+    setCollectionAccess,
+    addNFTAccessEntry,
+    addCollectionAccessEntry,
+    removeNFTAccessEntry,
+    removeCollectionAccessEntry,
   };
 }
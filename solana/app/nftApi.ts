@@ -1,9 +1,7 @@
 import {
   TOKEN_PROGRAM_ID,
-  createAssociatedTokenAccountInstruction,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
   getAssociatedTokenAddress,
-  createInitializeMintInstruction,
-  MINT_SIZE,
 } from "@solana/spl-token";
 import { Program } from "@project-serum/anchor";
 import { Rental } from "../target/types/rental";
@@ -17,6 +15,9 @@ const TOKEN_METADATA_PROGRAM_ID = new web3.PublicKey(
   "RTSE3BtLs2dDR482uuKMvdcGwWnbbQqUFEkxnTZC7FG" // Local deployment
 );
 
+// Editions tracked by each edition marker account
+const EDITION_MARKER_BIT_SIZE = 248;
+
 // Configure the client to use the local cluster.
 anchor.setProvider(anchor.AnchorProvider.env());
 const program = anchor.workspace.Rental as Program<Rental>;
@@ -61,48 +62,55 @@ export interface MintData {
   nftTokenAccount: web3.PublicKey;
 }
 
+export interface CreatorShare {
+  address: web3.PublicKey;
+  share: number;
+}
+
+export interface MintOptions {
+  // Shares sum to 100, defaults to the wallet at 100%
+  creators?: CreatorShare[];
+  sellerFeeBasisPoints?: number;
+  collectionMint?: web3.PublicKey;
+  // Program collection of `collectionMint`, authorized by the wallet. Lets the
+  // program verify its creator authority pda when listed in `creators`
+  collection?: web3.PublicKey;
+  // Defaults to a one of one, null for unlimited prints
+  maxSupply?: number | null;
+}
+
+// Pda the program verifies as creator
+export const getCreatorAuthorityPubkey = async (): Promise<web3.PublicKey> => {
+  return (
+    await web3.PublicKey.findProgramAddress(
+      [Buffer.from("creator")],
+      program.programId
+    )
+  )[0];
+};
+
 export function getNftAPI(program: Program<Rental>) {
   // Mint nft with program
-  async function mint(uri: string, title: string, symbol: string) {
-    const lamports = await connection.getMinimumBalanceForRentExemption(
-      MINT_SIZE
-    );
+  async function mint(
+    uri: string,
+    title: string,
+    symbol: string,
+    options: MintOptions = {}
+  ) {
+    const {
+      creators = [{ address: provider.wallet.publicKey, share: 100 }],
+      sellerFeeBasisPoints = 0,
+      collectionMint = null,
+      collection = null,
+      maxSupply = 0,
+    } = options;
+    // The program creates the mint and token account
     const mintKey: anchor.web3.Keypair = anchor.web3.Keypair.generate();
     const nftTokenAccount = await getAssociatedTokenAddress(
       mintKey.publicKey,
       provider.wallet.publicKey
     );
     console.log("NFT Account: ", nftTokenAccount.toBase58());
-
-    // Create accounts
-
-    const mint_tx = new anchor.web3.Transaction().add(
-      anchor.web3.SystemProgram.createAccount({
-        fromPubkey: provider.wallet.publicKey,
-        newAccountPubkey: mintKey.publicKey,
-        space: MINT_SIZE,
-        programId: TOKEN_PROGRAM_ID,
-        lamports,
-      }),
-      createInitializeMintInstruction(
-        mintKey.publicKey,
-        0,
-        provider.wallet.publicKey,
-        provider.wallet.publicKey
-      ),
-      createAssociatedTokenAccountInstruction(
-        provider.wallet.publicKey,
-        nftTokenAccount,
-        provider.wallet.publicKey,
-        mintKey.publicKey
-      )
-    );
-    const res = await provider.sendAll([{ tx: mint_tx, signers: [mintKey] }]);
-    // console.log(
-    //   await connection.getParsedAccountInfo(mintKey.publicKey)
-    // );
-
-    console.log("Account: ", res);
     console.log("Mint key: ", mintKey.publicKey.toString());
     console.log("User: ", provider.wallet.publicKey.toString());
 
@@ -112,13 +120,28 @@ export function getNftAPI(program: Program<Rental>) {
     console.log("Metadata address: ", metadataAddress.toBase58());
     console.log("MasterEdition: ", masterEdition.toBase58());
 
+    const [config] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("config")],
+      program.programId
+    );
     const tx = await program.methods
-      .mintNft(mintKey.publicKey, uri, title, symbol)
+      .mintNft(
+        uri,
+        title,
+        symbol,
+        creators,
+        sellerFeeBasisPoints,
+        collectionMint,
+        maxSupply === null ? null : new anchor.BN(maxSupply)
+      )
       .accounts({
+        config,
+        creatorAuthority: await getCreatorAuthorityPubkey(),
         mintAuthority: provider.wallet.publicKey,
         mint: mintKey.publicKey,
         tokenAccount: nftTokenAccount,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         metadata: metadataAddress,
         tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
         payer: provider.wallet.publicKey,
@@ -126,6 +149,19 @@ export function getNftAPI(program: Program<Rental>) {
         rent: anchor.web3.SYSVAR_RENT_PUBKEY,
         masterEdition: masterEdition,
       })
+      .remainingAccounts(
+        collection
+          ? [
+              { pubkey: collection, isSigner: false, isWritable: false },
+              {
+                pubkey: provider.wallet.publicKey,
+                isSigner: true,
+                isWritable: false,
+              },
+            ]
+          : []
+      )
+      .signers([mintKey])
       .rpc();
     console.log("Your transaction signature", tx);
 
@@ -138,6 +174,54 @@ export function getNftAPI(program: Program<Rental>) {
     };
   }
 
+  // Prints edition number `edition` of a master edition the wallet holds
+  async function printEdition(masterMint: web3.PublicKey, edition: number) {
+    const newMint = anchor.web3.Keypair.generate();
+    const [editionMarker] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("metadata"),
+        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+        masterMint.toBuffer(),
+        Buffer.from("edition"),
+        Buffer.from(Math.floor(edition / EDITION_MARKER_BIT_SIZE).toString()),
+      ],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+    const [config] = await web3.PublicKey.findProgramAddress(
+      [Buffer.from("config")],
+      program.programId
+    );
+    await program.methods
+      .printEdition(new anchor.BN(edition))
+      .accounts({
+        newMint: newMint.publicKey,
+        newTokenAccount: await getAssociatedTokenAddress(
+          newMint.publicKey,
+          provider.wallet.publicKey
+        ),
+        newMetadata: await getMetadataPubkey(newMint.publicKey),
+        newEdition: await getMasterEditionPubkey(newMint.publicKey),
+        masterMint,
+        masterTokenAccount: await getAssociatedTokenAddress(
+          masterMint,
+          provider.wallet.publicKey
+        ),
+        masterMetadata: await getMetadataPubkey(masterMint),
+        masterEdition: await getMasterEditionPubkey(masterMint),
+        editionMarker,
+        owner: provider.wallet.publicKey,
+        tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        config,
+      })
+      .signers([newMint])
+      .rpc();
+    return newMint.publicKey;
+  }
+
   async function verifies() {
     // const tx = await program.methods
     //   .verifyNft()
@@ -154,6 +238,7 @@ export function getNftAPI(program: Program<Rental>) {
 
   return {
     mint,
+    printEdition,
     verifies,
   };
 }
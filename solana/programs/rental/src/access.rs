@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+use crate::token_interface::token_account;
+use crate::{Collection, ErrorCode, Nft};
+
+pub fn set_nft_access(
+    ctx: Context<SetNftAccess>,
+    _mint: Pubkey,
+    access: AccessPolicy,
+) -> Result<()> {
+    ctx.accounts.nft.access = access;
+    Ok(())
+}
+
+pub fn set_collection_access(
+    ctx: Context<SetCollectionAccess>,
+    _symbol: String,
+    access: AccessPolicy,
+) -> Result<()> {
+    ctx.accounts.collection.access = access;
+    Ok(())
+}
+
+pub fn add_nft_access_entry(
+    ctx: Context<AddNftAccessEntry>,
+    _mint: Pubkey,
+    wallet: Pubkey,
+) -> Result<()> {
+    let entry = &mut ctx.accounts.entry;
+    entry.scope = ctx.accounts.nft.key();
+    entry.wallet = wallet;
+    Ok(())
+}
+
+pub fn add_collection_access_entry(
+    ctx: Context<AddCollectionAccessEntry>,
+    _symbol: String,
+    wallet: Pubkey,
+) -> Result<()> {
+    let entry = &mut ctx.accounts.entry;
+    entry.scope = ctx.accounts.collection.key();
+    entry.wallet = wallet;
+    Ok(())
+}
+
+pub fn remove_nft_access_entry(_ctx: Context<RemoveNftAccessEntry>, _mint: Pubkey) -> Result<()> {
+    Ok(())
+}
+
+pub fn remove_collection_access_entry(
+    _ctx: Context<RemoveCollectionAccessEntry>,
+    _symbol: String,
+) -> Result<()> {
+    Ok(())
+}
+
+// Checks the renter against the access policy of `scope`, an nft or collection pda.
+// `account` is the scope access entry for allow & deny lists, or the renter
+// token account when holding a token is required
+pub fn check_access(
+    access: &AccessPolicy,
+    scope: &Pubkey,
+    renter: &Pubkey,
+    account: &AccountInfo,
+    proof: &[[u8; 32]],
+    program_id: &Pubkey,
+) -> Result<()> {
+    let allowed = match access {
+        AccessPolicy::Open => true,
+        AccessPolicy::Allowlist => has_entry(scope, renter, account, program_id)?,
+        AccessPolicy::Denylist => !has_entry(scope, renter, account, program_id)?,
+        AccessPolicy::MerkleRoot { root } => verify_proof(root, renter, proof),
+        AccessPolicy::TokenHolder { mint } => {
+            let token_account = token_account(account)?;
+            token_account.owner == *renter
+                && token_account.mint == *mint
+                && token_account.amount > 0
+        }
+    };
+    if !allowed {
+        return err!(ErrorCode::AccessDenied);
+    }
+    Ok(())
+}
+
+fn has_entry(
+    scope: &Pubkey,
+    wallet: &Pubkey,
+    account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<bool> {
+    let (pda, _bump_seed) = Pubkey::find_program_address(
+        &[b"access".as_ref(), scope.as_ref(), wallet.as_ref()],
+        program_id,
+    );
+    if pda != account.key() {
+        return err!(ErrorCode::InvalidAccessAccount);
+    }
+    Ok(!account.data_is_empty())
+}
+
+// Leaves are keccak(wallet), pairs are hashed in sorted order
+fn verify_proof(root: &[u8; 32], wallet: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    let mut node = keccak::hash(wallet.as_ref()).0;
+    for sibling in proof {
+        node = if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).0
+        } else {
+            keccak::hashv(&[sibling, &node]).0
+        };
+    }
+    node == *root
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct SetNftAccess<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct SetCollectionAccess<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey, wallet: Pubkey)]
+pub struct AddNftAccessEntry<'info> {
+    #[account(
+        init, payer = owner,
+        space = AccessEntry::SIZE,
+        seeds = [b"access".as_ref(), nft.key().as_ref(), wallet.as_ref()],
+        bump)]
+    pub entry: Account<'info, AccessEntry>,
+    #[account(
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String, wallet: Pubkey)]
+pub struct AddCollectionAccessEntry<'info> {
+    #[account(
+        init, payer = authority,
+        space = AccessEntry::SIZE,
+        seeds = [b"access".as_ref(), collection.key().as_ref(), wallet.as_ref()],
+        bump)]
+    pub entry: Account<'info, AccessEntry>,
+    #[account(
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct RemoveNftAccessEntry<'info> {
+    #[account(mut,
+        seeds = [b"access".as_ref(), nft.key().as_ref(), entry.wallet.as_ref()],
+        bump,
+        close = owner)]
+    pub entry: Account<'info, AccessEntry>,
+    #[account(
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct RemoveCollectionAccessEntry<'info> {
+    #[account(mut,
+        seeds = [b"access".as_ref(), collection.key().as_ref(), entry.wallet.as_ref()],
+        bump,
+        close = authority)]
+    pub entry: Account<'info, AccessEntry>,
+    #[account(
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum AccessPolicy {
+    Open,
+    // Renters need an access entry
+    Allowlist,
+    // Renters with an access entry are rejected
+    Denylist,
+    // Renters prove their wallet is part of the tree
+    MerkleRoot { root: [u8; 32] },
+    // Renters hold a token of the mint
+    TokenHolder { mint: Pubkey },
+}
+
+impl Default for AccessPolicy {
+    fn default() -> Self {
+        AccessPolicy::Open
+    }
+}
+
+impl AccessPolicy {
+    pub const SIZE: usize = 1 + 32;
+}
+
+#[account]
+pub struct AccessEntry {
+    // Nft or collection pda
+    pub scope: Pubkey,
+    pub wallet: Pubkey,
+}
+
+impl AccessEntry {
+    const SIZE: usize = 8   // discriminator
+    + 32 // scope
+    + 32; // wallet
+}
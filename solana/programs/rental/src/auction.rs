@@ -0,0 +1,416 @@
+use anchor_lang::prelude::*;
+
+use crate::access::check_access;
+use crate::bookings::{check_not_booked, transfer_escrow};
+use crate::config::Config;
+use crate::delegation::freeze_rented;
+use crate::math::rental_end;
+use crate::records::RentalRecord;
+use crate::rented_metadata::sync_rented_metadata;
+use crate::time::now;
+use crate::vault::Vault;
+use crate::{transfer_funds, Collection, ErrorCode, Nft};
+
+pub fn start_rental_auction(
+    ctx: Context<StartRentalAuction>,
+    mint: Pubkey,
+    days: u32,
+    min_bid: u64,
+    bidding_end: i64,
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let auction = &mut ctx.accounts.auction;
+
+    let timestamp = now()?;
+
+    // Check auction validity
+    if !nft.rental_enabled {
+        return err!(ErrorCode::NftNotListed);
+    }
+    if days == 0 || days > nft.rental_max_days {
+        return err!(ErrorCode::InvalidRentalDuration);
+    }
+    ctx.accounts.collection.policy.check_rental(nft, days)?;
+    if bidding_end <= timestamp {
+        return err!(ErrorCode::InvalidBiddingPeriod);
+    }
+
+    auction.mint = mint;
+    auction.payment_mint = nft.payment_mint;
+    auction.days = days;
+    auction.min_bid = min_bid;
+    auction.bidding_end = bidding_end;
+    auction.bids = vec![];
+    Ok(())
+}
+
+// Remaining accounts, for spl payment mints: [bidder token account, auction
+// vault, token program, payment mint]
+pub fn place_bid<'info>(
+    ctx: Context<'_, '_, '_, 'info, PlaceBid<'info>>,
+    amount: u64,
+    nft_proof: Vec<[u8; 32]>,
+    collection_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let auction = &mut ctx.accounts.auction;
+    let bidder = &ctx.accounts.bidder;
+
+    let timestamp = now()?;
+    if timestamp >= auction.bidding_end {
+        return err!(ErrorCode::AuctionEnded);
+    }
+    // Only renters allowed by the access policies may win the rental
+    check_access(
+        &nft.access,
+        &nft.key(),
+        &bidder.key(),
+        &ctx.accounts.nft_access,
+        &nft_proof,
+        ctx.program_id,
+    )?;
+    check_access(
+        &ctx.accounts.collection.access,
+        &nft.collection,
+        &bidder.key(),
+        &ctx.accounts.collection_access,
+        &collection_proof,
+        ctx.program_id,
+    )?;
+    if amount < auction.min_bid {
+        return err!(ErrorCode::BidTooLow);
+    }
+    if let Some(highest) = auction.highest_bid() {
+        if amount <= highest.amount {
+            return err!(ErrorCode::BidTooLow);
+        }
+    }
+
+    // Raising a bid only escrows the difference
+    let previous = auction.bids.iter().position(|b| b.bidder == bidder.key());
+    let escrowed = previous.map_or(0, |index| auction.bids[index].amount);
+    if previous.is_none() && auction.bids.len() >= RentalAuction::BIDS_MAX_LEN {
+        return err!(ErrorCode::TooManyBids);
+    }
+    let difference = amount
+        .checked_sub(escrowed)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if auction.payment_mint == Pubkey::default() {
+        transfer_funds(
+            &bidder.to_account_info(),
+            &auction.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            difference,
+        )?;
+    } else {
+        let (from, vault_accounts) = ctx
+            .remaining_accounts
+            .split_first()
+            .ok_or(ErrorCode::MissingPaymentAccounts)?;
+        Vault::from_accounts(vault_accounts, &auction.key(), &auction.payment_mint)?.deposit(
+            from,
+            &bidder.to_account_info(),
+            difference,
+        )?;
+    }
+
+    match previous {
+        Some(index) => auction.bids[index].amount = amount,
+        None => auction.bids.push(Bid {
+            bidder: bidder.key(),
+            amount,
+        }),
+    }
+    Ok(())
+}
+
+// Remaining accounts: for spl payment mints [auction vault, token program,
+// payment mint, owner token account], then the bidder refund accounts, then
+// the delegation accounts of a delegated nft
+pub fn settle_rental_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettleRentalAuction<'info>>,
+) -> Result<()> {
+    let nft = &mut ctx.accounts.nft;
+    let auction = &ctx.accounts.auction;
+    let owner = &ctx.accounts.owner;
+    let auction_info = auction.to_account_info();
+
+    let timestamp = now()?;
+    if timestamp < auction.bidding_end {
+        return err!(ErrorCode::AuctionNotEnded);
+    }
+
+    // Without bids nothing is rented, the auction is cancelled instead
+    let winner = match auction.highest_bid() {
+        Some(winner) => winner.clone(),
+        None => return err!(ErrorCode::NoBids),
+    };
+
+    // The window starts at settlement, the ongoing rental keeping its renter
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+    let rented_until = rental_end(timestamp, auction.days)?;
+    check_not_booked(&ctx.accounts.bookings, timestamp, rented_until)?;
+
+    // Refund the other bidders, passed in bid order as remaining accounts,
+    // their token accounts for spl payment mints
+    let bump = *ctx.bumps.get("auction").unwrap();
+    let seeds: &[&[u8]] = &[b"auction".as_ref(), auction.mint.as_ref(), &[bump]];
+    let (vault, owner_account, accounts) = if auction.payment_mint == Pubkey::default() {
+        (None, owner, ctx.remaining_accounts)
+    } else {
+        if ctx.remaining_accounts.len() < 4 {
+            return err!(ErrorCode::MissingPaymentAccounts);
+        }
+        let vault = Vault::from_accounts(
+            ctx.remaining_accounts,
+            &auction_info.key(),
+            &auction.payment_mint,
+        )?;
+        (
+            Some(vault),
+            &ctx.remaining_accounts[3],
+            &ctx.remaining_accounts[4..],
+        )
+    };
+    let (bidders, delegation_accounts) =
+        accounts.split_at(accounts.len().min(auction.bids.len() - 1));
+    refund_bids(
+        &auction_info,
+        vault.as_ref().map(|vault| (vault, seeds)),
+        auction.bids.iter().filter(|b| b.bidder != winner.bidder),
+        bidders,
+    )?;
+
+    // Pay the owner with the winning bid
+    match &vault {
+        None => transfer_escrow(&auction_info, owner, winner.amount)?,
+        Some(vault) => vault.withdraw(
+            &auction_info,
+            seeds,
+            owner_account,
+            &owner.key(),
+            winner.amount,
+        )?,
+    }
+
+    // Rent nft
+    nft.start_rental(
+        &mut ctx.accounts.collection,
+        winner.bidder,
+        rented_until,
+        auction.days,
+        winner.amount,
+    )?;
+    ctx.accounts
+        .record
+        .start(nft, ctx.accounts.payer.key(), timestamp, winner.amount);
+    sync_rented_metadata(
+        &ctx.accounts.collection,
+        &ctx.accounts.metadata,
+        &ctx.accounts.token_metadata_program,
+        &nft.mint,
+        true,
+        ctx.program_id,
+    )?;
+    if nft.token_account != Pubkey::default() {
+        freeze_rented(nft, delegation_accounts, ctx.program_id)?;
+    }
+    Ok(())
+}
+
+// Remaining accounts: for spl payment mints [auction vault, token program,
+// payment mint], then the bidder refund accounts
+pub fn cancel_rental_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelRentalAuction<'info>>,
+) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+    let auction_info = auction.to_account_info();
+    // Every bidder is refunded, passed in bid order as remaining accounts,
+    // their token accounts for spl payment mints
+    if auction.payment_mint == Pubkey::default() {
+        return refund_bids(
+            &auction_info,
+            None,
+            auction.bids.iter(),
+            ctx.remaining_accounts,
+        );
+    }
+    let bump = *ctx.bumps.get("auction").unwrap();
+    let seeds: &[&[u8]] = &[b"auction".as_ref(), auction.mint.as_ref(), &[bump]];
+    let vault = Vault::from_accounts(
+        ctx.remaining_accounts,
+        &auction_info.key(),
+        &auction.payment_mint,
+    )?;
+    refund_bids(
+        &auction_info,
+        Some((&vault, seeds)),
+        auction.bids.iter(),
+        &ctx.remaining_accounts[3..],
+    )
+}
+
+// Refunds the bids in lamports, or from the auction vault with the auction
+// seeds, to the matching bidders or bidder token accounts
+fn refund_bids<'a, 'b>(
+    auction_info: &AccountInfo<'a>,
+    vault: Option<(&Vault<'_, 'a>, &[&[u8]])>,
+    bids: impl Iterator<Item = &'b Bid>,
+    bidders: &[AccountInfo<'a>],
+) -> Result<()> {
+    let mut bidders = bidders.iter();
+    for bid in bids {
+        let bidder = bidders.next().ok_or(ErrorCode::MissingBidderAccount)?;
+        match vault {
+            None => {
+                if bidder.key() != bid.bidder {
+                    return err!(ErrorCode::MissingBidderAccount);
+                }
+                transfer_escrow(auction_info, bidder, bid.amount)?;
+            }
+            // The token account owner is checked against the bidder
+            Some((vault, seeds)) => {
+                vault.withdraw(auction_info, seeds, bidder, &bid.bidder, bid.amount)?
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct StartRentalAuction<'info> {
+    #[account(
+        init, payer = owner,
+        space = RentalAuction::SIZE,
+        seeds = [b"auction".as_ref(), mint.as_ref()],
+        bump)]
+    pub auction: Account<'info, RentalAuction>,
+    #[account(
+        seeds = [b"nft".as_ref(), mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut,
+        seeds = [b"auction".as_ref(), auction.mint.as_ref()],
+        bump)]
+    pub auction: Account<'info, RentalAuction>,
+    #[account(
+        seeds = [b"nft".as_ref(), auction.mint.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    #[account(address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SettleRentalAuction<'info> {
+    #[account(mut,
+        seeds = [b"auction".as_ref(), nft.mint.as_ref()],
+        bump,
+        close = owner)]
+    pub auction: Account<'info, RentalAuction>,
+    #[account(mut, has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: bookings pda, may not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    #[account(
+        init, payer = payer,
+        space = RentalRecord::SIZE,
+        seeds = [b"rental_record".as_ref(), nft.mint.as_ref(), nft.rental_count.to_le_bytes().as_ref()],
+        bump)]
+    pub record: Account<'info, RentalRecord>,
+    /// CHECK: metadata of the nft, checked when the collection has rented metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: checked when the collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: checked against the nft owner
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    // Pays for the rental record
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRentalAuction<'info> {
+    #[account(mut,
+        seeds = [b"auction".as_ref(), nft.mint.as_ref()],
+        bump,
+        close = owner)]
+    pub auction: Account<'info, RentalAuction>,
+    #[account(has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[account]
+pub struct RentalAuction {
+    pub mint: Pubkey,
+    // Payment mint of the bids, the nft one at the start of the auction
+    pub payment_mint: Pubkey,
+    // Rental window auctioned
+    pub days: u32,
+    pub min_bid: u64,
+    pub bidding_end: i64,
+    // Escrowed bids, for the whole window, in lamports or in the auction vault
+    pub bids: Vec<Bid>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Bid {
+    pub bidder: Pubkey,
+    pub amount: u64,
+}
+
+impl RentalAuction {
+    pub const BIDS_MAX_LEN: usize = 10;
+
+    pub const SIZE: usize = 8   // discriminator
+    + 32 // mint
+    + 32 // payment_mint
+    + 4 // days
+    + 8 // min_bid
+    + 8 // bidding_end
+    + 4 + RentalAuction::BIDS_MAX_LEN * (32 + 8); // bids
+
+    pub fn highest_bid(&self) -> Option<&Bid> {
+        self.bids.iter().max_by_key(|b| b.amount)
+    }
+}
@@ -0,0 +1,399 @@
+use anchor_lang::prelude::*;
+
+use crate::access::check_access;
+use crate::config::Config;
+use crate::math::{percent_of, rental_amount, rental_end};
+use crate::time::now;
+use crate::vault::Vault;
+use crate::{transfer_funds, Collection, ErrorCode, Nft};
+
+pub fn init_bookings(
+    ctx: Context<InitBookings>,
+    mint: Pubkey,
+    cancel_notice_s: i64,
+    cancel_refund_percent: u8,
+) -> Result<()> {
+    require!(
+        cancel_notice_s >= 0 && cancel_refund_percent <= 100,
+        ErrorCode::InvalidCancellationRules
+    );
+    let bookings = &mut ctx.accounts.bookings;
+    bookings.mint = mint;
+    bookings.cancel_notice_s = cancel_notice_s;
+    bookings.cancel_refund_percent = cancel_refund_percent;
+    bookings.reservations = vec![];
+    Ok(())
+}
+
+pub fn update_bookings(
+    ctx: Context<UpdateBookings>,
+    cancel_notice_s: i64,
+    cancel_refund_percent: u8,
+) -> Result<()> {
+    require!(
+        cancel_notice_s >= 0 && cancel_refund_percent <= 100,
+        ErrorCode::InvalidCancellationRules
+    );
+    let bookings = &mut ctx.accounts.bookings;
+    bookings.cancel_notice_s = cancel_notice_s;
+    bookings.cancel_refund_percent = cancel_refund_percent;
+    Ok(())
+}
+
+pub fn close_bookings(ctx: Context<CloseBookings>) -> Result<()> {
+    // Reservations are paid, they must be activated or cancelled first
+    if !ctx.accounts.bookings.reservations.is_empty() {
+        return err!(ErrorCode::NftBooked);
+    }
+    Ok(())
+}
+
+// Remaining accounts, for spl payment mints: [renter token account, bookings
+// vault, token program, payment mint]
+pub fn book_nft<'info>(
+    ctx: Context<'_, '_, '_, 'info, BookNft<'info>>,
+    start: i64,
+    days: u32,
+    nft_proof: Vec<[u8; 32]>,
+    collection_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let bookings = &mut ctx.accounts.bookings;
+    let renter = &ctx.accounts.renter;
+
+    let timestamp = now()?;
+
+    // Check booking validity
+    if !nft.rental_enabled {
+        return err!(ErrorCode::NftNotListed);
+    }
+    if days == 0 || days > nft.rental_max_days {
+        return err!(ErrorCode::InvalidRentalDuration);
+    }
+    ctx.accounts.collection.policy.check_rental(nft, days)?;
+    check_access(
+        &nft.access,
+        &nft.key(),
+        &renter.key(),
+        &ctx.accounts.nft_access,
+        &nft_proof,
+        ctx.program_id,
+    )?;
+    check_access(
+        &ctx.accounts.collection.access,
+        &nft.collection,
+        &renter.key(),
+        &ctx.accounts.collection_access,
+        &collection_proof,
+        ctx.program_id,
+    )?;
+    let end = rental_end(start, days)?;
+    if start < timestamp || start < nft.rented_until {
+        return err!(ErrorCode::InvalidBookingWindow);
+    }
+    if bookings.overlaps(start, end) {
+        return err!(ErrorCode::NftBooked);
+    }
+    if bookings.reservations.len() >= Bookings::RESERVATIONS_MAX_LEN {
+        return err!(ErrorCode::TooManyReservations);
+    }
+
+    // Escrow the payment in the bookings account, or its vault
+    let amount = rental_amount(nft.rental_price, days)?;
+    if nft.payment_mint == Pubkey::default() {
+        transfer_funds(
+            &renter.to_account_info(),
+            &bookings.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+    } else {
+        let (from, vault_accounts) = ctx
+            .remaining_accounts
+            .split_first()
+            .ok_or(ErrorCode::MissingPaymentAccounts)?;
+        Vault::from_accounts(vault_accounts, &bookings.key(), &nft.payment_mint)?.deposit(
+            from,
+            &renter.to_account_info(),
+            amount,
+        )?;
+    }
+
+    bookings.reservations.push(Reservation {
+        start,
+        end,
+        renter: renter.key(),
+        amount,
+    });
+    Ok(())
+}
+
+// Remaining accounts, for spl payment mints: [renter token account, owner
+// token account, bookings vault, token program, payment mint]
+pub fn cancel_booking<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelBooking<'info>>,
+    start: i64,
+) -> Result<()> {
+    let payment_mint = ctx.accounts.nft.payment_mint;
+    let bookings = &mut ctx.accounts.bookings;
+    let renter = &ctx.accounts.renter;
+    let owner = &ctx.accounts.owner;
+
+    let timestamp = now()?;
+
+    let index = bookings
+        .reservations
+        .iter()
+        .position(|r| r.start == start && r.renter == renter.key())
+        .ok_or(ErrorCode::ReservationNotFound)?;
+    // Started reservations can't be cancelled anymore
+    if bookings.reservations[index].start <= timestamp {
+        return err!(ErrorCode::InvalidBookingWindow);
+    }
+    let reservation = bookings.reservations.remove(index);
+
+    // Full refund with enough notice, partial refund otherwise
+    let refund = if reservation.start - timestamp >= bookings.cancel_notice_s {
+        reservation.amount
+    } else {
+        percent_of(reservation.amount, bookings.cancel_refund_percent)?
+    };
+    let remainder = reservation
+        .amount
+        .checked_sub(refund)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let bookings_info = bookings.to_account_info();
+    if payment_mint == Pubkey::default() {
+        transfer_escrow(&bookings_info, &renter.to_account_info(), refund)?;
+        return transfer_escrow(&bookings_info, owner, remainder);
+    }
+    let accounts = ctx.remaining_accounts;
+    if accounts.len() < 2 {
+        return err!(ErrorCode::MissingPaymentAccounts);
+    }
+    let bump = *ctx.bumps.get("bookings").unwrap();
+    let seeds: &[&[u8]] = &[b"bookings".as_ref(), bookings.mint.as_ref(), &[bump]];
+    let vault = Vault::from_accounts(&accounts[2..], &bookings_info.key(), &payment_mint)?;
+    vault.withdraw(&bookings_info, seeds, &accounts[0], &renter.key(), refund)?;
+    vault.withdraw(&bookings_info, seeds, &accounts[1], &owner.key(), remainder)
+}
+
+// Pays `amount` out of the bookings escrow to the nft owner, in lamports or
+// from the bookings vault. `payment_accounts` are the rent_nft ones, for spl
+// payment mints: [renter token account, owner token account, token program,
+// payment mint, bookings vault]
+pub fn pay_bookings_escrow<'info>(
+    bookings_info: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    nft: &Nft,
+    payment_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if nft.payment_mint == Pubkey::default() {
+        return transfer_escrow(bookings_info, owner, amount);
+    }
+    if amount == 0 {
+        return Ok(());
+    }
+    if payment_accounts.len() < 5 {
+        return err!(ErrorCode::MissingPaymentAccounts);
+    }
+    let (_pda, bump) =
+        Pubkey::find_program_address(&[b"bookings".as_ref(), nft.mint.as_ref()], program_id);
+    let seeds: &[&[u8]] = &[b"bookings".as_ref(), nft.mint.as_ref(), &[bump]];
+    Vault::load(
+        &payment_accounts[4],
+        &payment_accounts[2],
+        &payment_accounts[3],
+        &bookings_info.key(),
+        &nft.payment_mint,
+    )?
+    .withdraw(
+        bookings_info,
+        seeds,
+        &payment_accounts[1],
+        &owner.key(),
+        amount,
+    )
+}
+
+// Fails if the bookings account, when initialized, has a reservation
+// overlapping [start, end)
+pub fn check_not_booked(bookings_info: &AccountInfo, start: i64, end: i64) -> Result<()> {
+    if bookings_info.data_is_empty() {
+        return Ok(());
+    }
+    let bookings = Account::<Bookings>::try_from(bookings_info)?;
+    if bookings.overlaps(start, end) {
+        return err!(ErrorCode::NftBooked);
+    }
+    Ok(())
+}
+
+// Whether the nft has paid reservations still to start
+pub fn has_reservations(bookings_info: &AccountInfo) -> Result<bool> {
+    if bookings_info.data_is_empty() {
+        return Ok(false);
+    }
+    let bookings = Account::<Bookings>::try_from(bookings_info)?;
+    Ok(!bookings.reservations.is_empty())
+}
+
+// Moves lamports out of a program owned escrow account
+pub fn transfer_escrow<'a>(
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    lamports: u64,
+) -> Result<()> {
+    let from_lamports = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ErrorCode::InsufficientFunds)?;
+    let to_lamports = to
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ErrorCode::MathOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_lamports;
+    **to.try_borrow_mut_lamports()? = to_lamports;
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct InitBookings<'info> {
+    #[account(
+        init, payer = owner,
+        space = Bookings::SIZE,
+        seeds = [b"bookings".as_ref(), mint.as_ref()],
+        bump)]
+    pub bookings: Account<'info, Bookings>,
+    #[account(
+        seeds = [b"nft".as_ref(), mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBookings<'info> {
+    #[account(mut,
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump)]
+    pub bookings: Account<'info, Bookings>,
+    #[account(has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBookings<'info> {
+    #[account(mut,
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump,
+        close = owner)]
+    pub bookings: Account<'info, Bookings>,
+    #[account(has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BookNft<'info> {
+    #[account(mut,
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump)]
+    pub bookings: Account<'info, Bookings>,
+    pub nft: Account<'info, Nft>,
+    #[account(address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct CancelBooking<'info> {
+    #[account(mut,
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump)]
+    pub bookings: Account<'info, Bookings>,
+    #[account(has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    /// CHECK: checked against the nft owner
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+}
+
+#[account]
+pub struct Bookings {
+    pub mint: Pubkey,
+    // Cancellation rules: full refund with at least `cancel_notice_s` of
+    // notice, `cancel_refund_percent` of the amount otherwise
+    pub cancel_notice_s: i64,
+    pub cancel_refund_percent: u8,
+    // Upcoming paid reservations
+    pub reservations: Vec<Reservation>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct Reservation {
+    pub start: i64,
+    pub end: i64,
+    pub renter: Pubkey,
+    // Escrowed amount, in lamports or in the nft payment mint
+    pub amount: u64,
+}
+
+impl Bookings {
+    pub const RESERVATIONS_MAX_LEN: usize = 10;
+
+    pub const SIZE: usize = 8   // discriminator
+    + 32 // mint
+    + 8 // cancel_notice_s
+    + 1 // cancel_refund_percent
+    + 4 + Bookings::RESERVATIONS_MAX_LEN * (8 + 8 + 32 + 8); // reservations
+
+    pub fn overlaps(&self, start: i64, end: i64) -> bool {
+        self.reservations
+            .iter()
+            .any(|r| start < r.end && r.start < end)
+    }
+
+    // Removes and returns the renter's reservation running at `timestamp`
+    pub fn take_due(&mut self, renter: &Pubkey, timestamp: i64) -> Option<Reservation> {
+        let index = self
+            .reservations
+            .iter()
+            .position(|r| r.renter == *renter && r.start <= timestamp && timestamp < r.end)?;
+        Some(self.reservations.remove(index))
+    }
+
+    // Removes reservations that ended without being activated, returning
+    // their escrowed amount
+    pub fn prune_expired(&mut self, timestamp: i64) -> u64 {
+        let expired: u64 = self
+            .reservations
+            .iter()
+            .filter(|r| r.end <= timestamp)
+            .map(|r| r.amount)
+            .sum();
+        self.reservations.retain(|r| r.end > timestamp);
+        expired
+    }
+}
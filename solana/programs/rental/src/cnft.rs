@@ -0,0 +1,491 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+use crate::access::check_access;
+use crate::bookings::check_not_booked;
+use crate::config::Config;
+use crate::math::{rental_amount, rental_end};
+use crate::records::{terminate_rental_record, RentalRecord, TerminationReason};
+use crate::time::now;
+use crate::{transfer_payment, Collection, ErrorCode, Nft};
+
+// Compressed nfts (cNFT) are leaves of a concurrent merkle tree, without mint
+// nor token account. Listing one moves the leaf to the program custody pda,
+// which stays its owner & delegate until delisted: a bubblegum leaf delegate
+// may transfer or burn the leaf, renters are only recorded on the nft. The
+// asset id stands for the mint in the nft, bookings & record pdas. Leaf
+// transfers take the current tree root, the leaf proof being the trailing
+// remaining accounts
+
+pub const BUBBLEGUM_PROGRAM_ID: &str = "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY";
+pub const COMPRESSION_PROGRAM_ID: &str = "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK";
+pub const NOOP_PROGRAM_ID: &str = "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNPtsmV";
+
+// Anchor discriminator of the bubblegum transfer instruction
+const TRANSFER: [u8; 8] = [163, 52, 200, 231, 140, 3, 69, 186];
+
+// Lists the compressed nft `asset_id`, moving its leaf from the owner to the
+// program custody. The transfer proves the owner holds the leaf
+pub fn create_cnft<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateCnft<'info>>,
+    asset_id: Pubkey,
+    leaf: LeafArgs,
+    rental_max_days: u32,
+    rental_price: u64,
+    rental_enabled: bool,
+    payment_mint: Pubkey,
+) -> Result<()> {
+    let collection = &mut ctx.accounts.collection;
+    let owner = &ctx.accounts.owner;
+    let custody = &ctx.accounts.custody;
+
+    let merkle_tree = ctx.accounts.tree.merkle_tree.key();
+    if asset_id != asset_key(&merkle_tree, leaf.nonce) {
+        return err!(ErrorCode::InvalidAssetId);
+    }
+    collection
+        .policy
+        .check_listing(rental_max_days, rental_price, &payment_mint)?;
+
+    let cnft = &mut ctx.accounts.cnft;
+    cnft.asset_id = asset_id;
+    cnft.merkle_tree = merkle_tree;
+    cnft.data_hash = leaf.data_hash;
+    cnft.creator_hash = leaf.creator_hash;
+    cnft.nonce = leaf.nonce;
+    cnft.index = leaf.index;
+
+    ctx.accounts.tree.transfer(
+        owner,
+        owner,
+        custody,
+        cnft,
+        leaf.root,
+        ctx.remaining_accounts,
+        &[],
+    )?;
+
+    ctx.accounts.nft.list(
+        asset_id,
+        collection.key(),
+        owner.key(),
+        rental_max_days,
+        rental_price,
+        rental_enabled,
+        payment_mint,
+    );
+    collection.listing_count = collection
+        .listing_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+// Rents a compressed nft, its leaf staying in the program custody. The renter
+// is recorded on the nft rather than set as the bubblegum leaf delegate: the
+// delegate may transfer or burn the leaf, and revoking it on expiry would need
+// the owner or a fresh leaf proof. Apps check the nft renter, as for rented
+// nfts in custody or delegated ones.
+// Remaining accounts: the payment accounts of spl payment mints
+pub fn rent_cnft<'info>(
+    ctx: Context<'_, '_, '_, 'info, RentCnft<'info>>,
+    _asset_id: Pubkey,
+    days: u32,
+    nft_proof: Vec<[u8; 32]>,
+    collection_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let nft = &mut ctx.accounts.nft;
+    let collection = &mut ctx.accounts.collection;
+    let renter = &ctx.accounts.renter;
+
+    let timestamp = now()?;
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+    check_access(
+        &nft.access,
+        &nft.key(),
+        &renter.key(),
+        &ctx.accounts.nft_access,
+        &nft_proof,
+        ctx.program_id,
+    )?;
+    check_access(
+        &collection.access,
+        &collection.key(),
+        &renter.key(),
+        &ctx.accounts.collection_access,
+        &collection_proof,
+        ctx.program_id,
+    )?;
+    nft.check_rentable(&collection.policy, days)?;
+    let rented_until = rental_end(timestamp, days)?;
+    check_not_booked(&ctx.accounts.bookings, timestamp, rented_until)?;
+
+    let price = rental_amount(nft.rental_price, days)?;
+    transfer_payment(
+        &nft.payment_mint,
+        renter,
+        &ctx.accounts.owner,
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+        price,
+    )?;
+
+    nft.start_rental(collection, renter.key(), rented_until, days, price)?;
+    ctx.accounts
+        .record
+        .start(nft, renter.key(), timestamp, price);
+    Ok(())
+}
+
+// Releases an expired compressed nft rental, anyone can crank it
+pub fn release_cnft(ctx: Context<ReleaseCnft>, _asset_id: Pubkey) -> Result<()> {
+    let nft = &mut ctx.accounts.nft;
+    let collection = &mut ctx.accounts.collection;
+
+    let timestamp = now()?;
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+    if nft.renter == Pubkey::default() {
+        return err!(ErrorCode::NftNotRented);
+    }
+    nft.renter = Pubkey::default();
+    collection.active_rentals = collection
+        .active_rentals
+        .checked_sub(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    terminate_rental_record(
+        &ctx.accounts.record,
+        TerminationReason::Expired,
+        ctx.program_id,
+    )
+}
+
+// Delists a compressed nft, its leaf going back to the owner
+pub fn delete_cnft<'info>(
+    ctx: Context<'_, '_, '_, 'info, DeleteCnft<'info>>,
+    _asset_id: Pubkey,
+    root: [u8; 32],
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let collection = &mut ctx.accounts.collection;
+    let custody = &ctx.accounts.custody;
+
+    let timestamp = now()?;
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+    if !ctx.accounts.bookings.data_is_empty() {
+        return err!(ErrorCode::NftBooked);
+    }
+    if !ctx.accounts.auction.data_is_empty() {
+        return err!(ErrorCode::AuctionOpen);
+    }
+
+    let bump = *ctx.bumps.get("custody").unwrap();
+    ctx.accounts.tree.transfer(
+        custody,
+        custody,
+        &ctx.accounts.owner,
+        &ctx.accounts.cnft,
+        root,
+        ctx.remaining_accounts,
+        &[&[b"cnft_custody".as_ref(), &[bump]]],
+    )?;
+
+    collection.listing_count = collection
+        .listing_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if nft.renter != Pubkey::default() {
+        collection.active_rentals = collection
+            .active_rentals
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        terminate_rental_record(
+            &ctx.accounts.record,
+            TerminationReason::Delisted,
+            ctx.program_id,
+        )?;
+    }
+    Ok(())
+}
+
+// Asset id of the leaf minted with `nonce` in `merkle_tree`
+pub fn asset_key(merkle_tree: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"asset".as_ref(),
+            merkle_tree.as_ref(),
+            &nonce.to_le_bytes(),
+        ],
+        &BUBBLEGUM_PROGRAM_ID.parse::<Pubkey>().unwrap(),
+    )
+    .0
+}
+
+impl<'info> CompressedTree<'info> {
+    // Transfers the leaf to `new_leaf_owner`, which also becomes its delegate
+    #[allow(clippy::too_many_arguments)]
+    fn transfer(
+        &self,
+        leaf_owner: &AccountInfo<'info>,
+        leaf_delegate: &AccountInfo<'info>,
+        new_leaf_owner: &AccountInfo<'info>,
+        cnft: &CompressedNft,
+        root: [u8; 32],
+        proof: &[AccountInfo<'info>],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut data = TRANSFER.to_vec();
+        data.extend_from_slice(&root);
+        data.extend_from_slice(&cnft.data_hash);
+        data.extend_from_slice(&cnft.creator_hash);
+        data.extend_from_slice(&cnft.nonce.to_le_bytes());
+        data.extend_from_slice(&cnft.index.to_le_bytes());
+
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.tree_authority.key(), false),
+            AccountMeta::new_readonly(leaf_owner.key(), true),
+            AccountMeta::new_readonly(leaf_delegate.key(), false),
+            AccountMeta::new_readonly(new_leaf_owner.key(), false),
+            AccountMeta::new(self.merkle_tree.key(), false),
+            AccountMeta::new_readonly(self.log_wrapper.key(), false),
+            AccountMeta::new_readonly(self.compression_program.key(), false),
+            AccountMeta::new_readonly(self.system_program.key(), false),
+        ];
+        accounts.extend(
+            proof
+                .iter()
+                .map(|node| AccountMeta::new_readonly(node.key(), false)),
+        );
+        let mut infos = vec![
+            self.tree_authority.to_account_info(),
+            leaf_owner.clone(),
+            leaf_delegate.clone(),
+            new_leaf_owner.clone(),
+            self.merkle_tree.to_account_info(),
+            self.log_wrapper.to_account_info(),
+            self.compression_program.to_account_info(),
+            self.system_program.to_account_info(),
+            self.bubblegum_program.to_account_info(),
+        ];
+        infos.extend_from_slice(proof);
+        invoke_signed(
+            &Instruction {
+                program_id: self.bubblegum_program.key(),
+                accounts,
+                data,
+            },
+            &infos,
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+// Concurrent merkle tree of a compressed nft & the programs updating it
+#[derive(Accounts)]
+pub struct CompressedTree<'info> {
+    /// CHECK: checked by bubblegum
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: checked by the account compression program, and against the
+    /// listed tree once listed
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = NOOP_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub log_wrapper: UncheckedAccount<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = COMPRESSION_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = BUBBLEGUM_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Leaf of a compressed nft, as of the current tree `root`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LeafArgs {
+    pub root: [u8; 32],
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+#[derive(Accounts)]
+#[instruction(asset_id: Pubkey)]
+pub struct CreateCnft<'info> {
+    #[account(
+        init, payer = owner,
+        space = Nft::SIZE,
+        seeds = [b"nft".as_ref(), asset_id.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    #[account(
+        init, payer = owner,
+        space = CompressedNft::SIZE,
+        seeds = [b"cnft".as_ref(), asset_id.as_ref()],
+        bump)]
+    pub cnft: Account<'info, CompressedNft>,
+    #[account(mut)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: pda holding listed compressed nfts
+    #[account(
+        seeds = [b"cnft_custody".as_ref()],
+        bump)]
+    pub custody: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub tree: CompressedTree<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_asset_id: Pubkey)]
+pub struct RentCnft<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _asset_id.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    // Tells compressed nft listings apart
+    #[account(
+        seeds = [b"cnft".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub cnft: Account<'info, CompressedNft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: bookings pda, may not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    /// CHECK: nft access entry or renter token account, checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: collection access entry or renter token account, checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    #[account(
+        init, payer = renter,
+        space = RentalRecord::SIZE,
+        seeds = [b"rental_record".as_ref(), _asset_id.as_ref(), nft.rental_count.to_le_bytes().as_ref()],
+        bump)]
+    pub record: Account<'info, RentalRecord>,
+    /// CHECK: checked against the nft owner
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_asset_id: Pubkey)]
+pub struct ReleaseCnft<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    // Tells compressed nft listings apart
+    #[account(
+        seeds = [b"cnft".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub cnft: Account<'info, CompressedNft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: record of the latest rental, may be closed
+    #[account(mut,
+        seeds = [b"rental_record".as_ref(), _asset_id.as_ref(), nft.rental_count.saturating_sub(1).to_le_bytes().as_ref()],
+        bump)]
+    pub record: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_asset_id: Pubkey)]
+pub struct DeleteCnft<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _asset_id.as_ref()],
+        bump,
+        has_one = owner,
+        close = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut,
+        seeds = [b"cnft".as_ref(), _asset_id.as_ref()],
+        bump,
+        constraint = cnft.merkle_tree == tree.merkle_tree.key() @ ErrorCode::InvalidTree,
+        close = owner)]
+    pub cnft: Account<'info, CompressedNft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: bookings pda, must not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    /// CHECK: auction pda, must not be initialized
+    #[account(
+        seeds = [b"auction".as_ref(), _asset_id.as_ref()],
+        bump)]
+    pub auction: UncheckedAccount<'info>,
+    /// CHECK: record of the latest rental, may be closed
+    #[account(mut,
+        seeds = [b"rental_record".as_ref(), _asset_id.as_ref(), nft.rental_count.saturating_sub(1).to_le_bytes().as_ref()],
+        bump)]
+    pub record: UncheckedAccount<'info>,
+    /// CHECK: pda holding listed compressed nfts
+    #[account(
+        seeds = [b"cnft_custody".as_ref()],
+        bump)]
+    pub custody: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub tree: CompressedTree<'info>,
+}
+
+// Leaf of a listed compressed nft, its hashes & index staying the same while
+// the program holds it
+#[account]
+pub struct CompressedNft {
+    pub asset_id: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub data_hash: [u8; 32],
+    pub creator_hash: [u8; 32],
+    pub nonce: u64,
+    pub index: u32,
+}
+
+impl CompressedNft {
+    const SIZE: usize = 8   // discriminator
+    + 32 // asset_id
+    + 32 // merkle_tree
+    + 32 // data_hash
+    + 32 // creator_hash
+    + 8 // nonce
+    + 4; // index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discriminator_matches_bubblegum() {
+        use anchor_lang::solana_program::hash::hash;
+        assert_eq!(TRANSFER, hash(b"global:transfer").to_bytes()[..8]);
+    }
+}
@@ -0,0 +1,635 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount};
+use mpl_token_metadata::state::{Creator, Metadata, PREFIX};
+
+use crate::access::{check_access, AccessPolicy};
+use crate::config::Config;
+use crate::nft_utils::{
+    check_creators, create_master_edition, create_metadata, metadata_data, sign_creator,
+    update_metadata, verify_collection_item, CreatorShare, TOKEN_METADATA_PROGRAM_ID,
+};
+use crate::{transfer_payment, Collection, ErrorCode, Nft};
+
+// The collection pda is the mint, update and Metaplex collection authority of
+// the nfts minted under it. Mints are signed by the collection authority, or
+// open to anyone through the collection mint gate
+
+// Mints the Metaplex collection nft of the collection
+pub fn create_collection_mint(
+    ctx: Context<CreateCollectionMint>,
+    _symbol: String,
+    uri: String,
+) -> Result<()> {
+    let collection = &ctx.accounts.collection;
+    let authority = &ctx.accounts.authority;
+    let bump = *ctx.bumps.get("collection").unwrap();
+    let authority_key = authority.key();
+    let seeds: &[&[u8]] = &[
+        b"collection".as_ref(),
+        _symbol.as_ref(),
+        authority_key.as_ref(),
+        &[bump],
+    ];
+
+    mint_edition(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.rent.to_account_info(),
+        &collection.to_account_info(),
+        authority,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.metadata,
+        &ctx.accounts.master_edition,
+        collection.name.clone(),
+        collection.symbol.clone(),
+        uri,
+        creators(authority_key),
+        seller_fee_basis_points(collection),
+        None,
+        &[seeds],
+    )?;
+    sign_creator(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        authority,
+        &[],
+    )?;
+
+    ctx.accounts.collection.collection_mint = ctx.accounts.mint.key();
+    Ok(())
+}
+
+// Mints an nft verified in the collection and lists it with the collection
+// default terms
+pub fn mint_and_list(
+    ctx: Context<MintAndList>,
+    _symbol: String,
+    uri: String,
+    title: String,
+) -> Result<()> {
+    let collection_info = ctx.accounts.collection.to_account_info();
+    let authority = &ctx.accounts.authority;
+    let bump = *ctx.bumps.get("collection").unwrap();
+    let authority_key = authority.key();
+    let seeds: &[&[u8]] = &[
+        b"collection".as_ref(),
+        _symbol.as_ref(),
+        authority_key.as_ref(),
+        &[bump],
+    ];
+
+    let collection = &ctx.accounts.collection;
+    mint_edition(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.rent.to_account_info(),
+        &collection_info,
+        authority,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.metadata,
+        &ctx.accounts.master_edition,
+        title,
+        collection.symbol.clone(),
+        uri,
+        creators(authority_key),
+        seller_fee_basis_points(collection),
+        Some(collection.collection_mint),
+        &[seeds],
+    )?;
+    sign_creator(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        authority,
+        &[],
+    )?;
+    verify_collection_item(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        &collection_info,
+        authority,
+        &ctx.accounts.collection_mint.to_account_info(),
+        &ctx.accounts.collection_metadata,
+        &ctx.accounts.collection_master_edition,
+        &[seeds],
+    )?;
+
+    // List it
+    let collection = &mut ctx.accounts.collection;
+    let (rental_max_days, rental_price, payment_mint) = collection.policy.default_listing();
+    ctx.accounts.nft.list(
+        ctx.accounts.mint.key(),
+        collection.key(),
+        authority_key,
+        rental_max_days,
+        rental_price,
+        collection.policy.rental_enabled && rental_max_days > 0,
+        payment_mint,
+    );
+    collection.listing_count = collection
+        .listing_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+pub fn init_mint_gate(
+    ctx: Context<InitMintGate>,
+    _symbol: String,
+    price: u64,
+    payment_mint: Pubkey,
+    access: AccessPolicy,
+    max_supply: u32,
+) -> Result<()> {
+    let mint_gate = &mut ctx.accounts.mint_gate;
+    mint_gate.collection = ctx.accounts.collection.key();
+    mint_gate.price = price;
+    mint_gate.payment_mint = payment_mint;
+    mint_gate.access = access;
+    mint_gate.max_supply = max_supply;
+    mint_gate.minted = 0;
+    Ok(())
+}
+
+pub fn update_mint_gate(
+    ctx: Context<UpdateMintGate>,
+    _symbol: String,
+    price: u64,
+    payment_mint: Pubkey,
+    access: AccessPolicy,
+    max_supply: u32,
+) -> Result<()> {
+    let mint_gate = &mut ctx.accounts.mint_gate;
+    mint_gate.price = price;
+    mint_gate.payment_mint = payment_mint;
+    mint_gate.access = access;
+    mint_gate.max_supply = max_supply;
+    Ok(())
+}
+
+// Public mint in the collection, paid to the collection authority, the
+// collection pda signing in place of the authority.
+// Remaining accounts: the payment accounts for spl payment mints
+pub fn gated_mint(
+    ctx: Context<GatedMint>,
+    _symbol: String,
+    uri: String,
+    title: String,
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let collection_info = ctx.accounts.collection.to_account_info();
+    let mint_gate = &mut ctx.accounts.mint_gate;
+    let authority = &ctx.accounts.authority;
+    let minter = &ctx.accounts.minter;
+
+    if mint_gate.max_supply != 0 && mint_gate.minted >= mint_gate.max_supply {
+        return err!(ErrorCode::MintSoldOut);
+    }
+    // Allow & deny lists use the collection access entries
+    check_access(
+        &mint_gate.access,
+        &collection_info.key(),
+        &minter.key(),
+        &ctx.accounts.minter_access,
+        &proof,
+        ctx.program_id,
+    )?;
+    transfer_payment(
+        &mint_gate.payment_mint,
+        minter,
+        authority,
+        &ctx.accounts.system_program.to_account_info(),
+        ctx.remaining_accounts,
+        mint_gate.price,
+    )?;
+    mint_gate.minted = mint_gate
+        .minted
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let bump = *ctx.bumps.get("collection").unwrap();
+    let authority_key = authority.key();
+    let seeds: &[&[u8]] = &[
+        b"collection".as_ref(),
+        _symbol.as_ref(),
+        authority_key.as_ref(),
+        &[bump],
+    ];
+    // The authority didn't sign, the collection pda is the verified creator
+    let creators = vec![
+        Creator {
+            address: collection_info.key(),
+            verified: true,
+            share: 0,
+        },
+        Creator {
+            address: authority_key,
+            verified: false,
+            share: 100,
+        },
+    ];
+    let collection = &ctx.accounts.collection;
+    mint_edition(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.rent.to_account_info(),
+        &collection_info,
+        minter,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.token_account.to_account_info(),
+        &ctx.accounts.metadata,
+        &ctx.accounts.master_edition,
+        title,
+        collection.symbol.clone(),
+        uri,
+        creators,
+        seller_fee_basis_points(collection),
+        Some(collection.collection_mint),
+        &[seeds],
+    )?;
+    verify_collection_item(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        &collection_info,
+        minter,
+        &ctx.accounts.collection_mint.to_account_info(),
+        &ctx.accounts.collection_metadata,
+        &ctx.accounts.collection_master_edition,
+        &[seeds],
+    )?;
+    Ok(())
+}
+
+// Updates an nft minted under the collection, fields left to None are kept.
+// Setting `is_mutable` to false locks the metadata for good
+pub fn update_nft_metadata(
+    ctx: Context<UpdateNftMetadata>,
+    _symbol: String,
+    _mint: Pubkey,
+    name: Option<String>,
+    uri: Option<String>,
+    creators: Option<Vec<CreatorShare>>,
+    is_mutable: Option<bool>,
+) -> Result<()> {
+    let collection_info = ctx.accounts.collection.to_account_info();
+    let metadata = Metadata::from_account_info(&ctx.accounts.metadata)
+        .map_err(|_| error!(ErrorCode::InvalidMetadata))?;
+    require!(
+        metadata.update_authority == collection_info.key(),
+        ErrorCode::InvalidMetadata
+    );
+
+    let mut data = metadata_data(&metadata);
+    if let Some(name) = name {
+        data.name = name;
+    }
+    if let Some(uri) = uri {
+        data.uri = uri;
+    }
+    if let Some(creators) = creators {
+        check_creators(&creators, data.seller_fee_basis_points)?;
+        // Creators keep their verification, new ones have to sign it
+        let verified: Vec<Pubkey> = data
+            .creators
+            .iter()
+            .flatten()
+            .filter(|creator| creator.verified)
+            .map(|creator| creator.address)
+            .collect();
+        data.creators = Some(
+            creators
+                .into_iter()
+                .map(|creator| Creator {
+                    address: creator.address,
+                    verified: verified.contains(&creator.address),
+                    share: creator.share,
+                })
+                .collect(),
+        );
+    }
+
+    let bump = *ctx.bumps.get("collection").unwrap();
+    let authority_key = ctx.accounts.authority.key();
+    let seeds: &[&[u8]] = &[
+        b"collection".as_ref(),
+        _symbol.as_ref(),
+        authority_key.as_ref(),
+        &[bump],
+    ];
+    update_metadata(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        &collection_info,
+        Some(data),
+        is_mutable,
+        &[seeds],
+    )
+}
+
+// Mints a one of one edition of `mint` to `token_account`, the collection
+// pda signing as mint and update authority
+#[allow(clippy::too_many_arguments)]
+fn mint_edition<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    collection: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    master_edition: &AccountInfo<'info>,
+    title: String,
+    symbol: String,
+    uri: String,
+    creators: Vec<Creator>,
+    seller_fee_basis_points: u16,
+    collection_mint: Option<Pubkey>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let cpi_accounts = MintTo {
+        mint: mint.to_owned(),
+        to: token_account.to_owned(),
+        authority: collection.to_owned(),
+    };
+    token::mint_to(
+        CpiContext::new_with_signer(token_program.to_owned(), cpi_accounts, signer_seeds),
+        1,
+    )?;
+    create_metadata(
+        token_metadata_program,
+        metadata,
+        mint,
+        collection,
+        payer,
+        collection,
+        system_program,
+        rent,
+        title,
+        symbol,
+        uri,
+        Some(creators),
+        seller_fee_basis_points,
+        collection_mint,
+        true,
+        signer_seeds,
+    )?;
+    create_master_edition(
+        token_metadata_program,
+        master_edition,
+        mint,
+        collection,
+        payer,
+        metadata,
+        collection,
+        token_program,
+        system_program,
+        rent,
+        Some(0),
+        signer_seeds,
+    )
+}
+
+// The collection authority is the only creator
+fn creators(authority: Pubkey) -> Vec<Creator> {
+    vec![Creator {
+        address: authority,
+        verified: false,
+        share: 100,
+    }]
+}
+
+fn seller_fee_basis_points(collection: &Collection) -> u16 {
+    collection.royalties_percent.min(100) as u16 * 100
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct CreateCollectionMint<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump,
+        constraint = collection.collection_mint == Pubkey::default() @ ErrorCode::CollectionMintExists)]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        init, payer = authority,
+        mint::decimals = 0,
+        mint::authority = collection,
+        mint::freeze_authority = collection)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init, payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = authority)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct MintAndList<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump,
+        constraint = collection.collection_mint != Pubkey::default() @ ErrorCode::CollectionMintMissing)]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        init, payer = authority,
+        mint::decimals = 0,
+        mint::authority = collection,
+        mint::freeze_authority = collection)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init, payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = authority)]
+    pub token_account: Account<'info, TokenAccount>,
+    #[account(
+        init, payer = authority,
+        space = Nft::SIZE,
+        seeds = [b"nft".as_ref(), mint.key().as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    #[account(address = collection.collection_mint)]
+    pub collection_mint: Account<'info, Mint>,
+    /// CHECK: checked by the token metadata program
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: checked by the token metadata program
+    pub collection_master_edition: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct InitMintGate<'info> {
+    #[account(
+        init, payer = authority,
+        space = MintGate::SIZE,
+        seeds = [b"mint_gate".as_ref(), collection.key().as_ref()],
+        bump)]
+    pub mint_gate: Account<'info, MintGate>,
+    #[account(
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct UpdateMintGate<'info> {
+    #[account(mut,
+        seeds = [b"mint_gate".as_ref(), collection.key().as_ref()],
+        bump)]
+    pub mint_gate: Account<'info, MintGate>,
+    #[account(
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct GatedMint<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump,
+        constraint = collection.collection_mint != Pubkey::default() @ ErrorCode::CollectionMintMissing)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut,
+        seeds = [b"mint_gate".as_ref(), collection.key().as_ref()],
+        bump)]
+    pub mint_gate: Account<'info, MintGate>,
+    #[account(
+        init, payer = minter,
+        mint::decimals = 0,
+        mint::authority = collection,
+        mint::freeze_authority = collection)]
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init, payer = minter,
+        associated_token::mint = mint,
+        associated_token::authority = minter)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: created by the token metadata program
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    #[account(address = collection.collection_mint)]
+    pub collection_mint: Account<'info, Mint>,
+    /// CHECK: checked by the token metadata program
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: checked by the token metadata program
+    pub collection_master_edition: UncheckedAccount<'info>,
+    /// CHECK: collection authority, checked through the collection seeds
+    #[account(mut)]
+    pub authority: UncheckedAccount<'info>,
+    /// CHECK: collection access entry or minter token account, checked against the gate access policy
+    pub minter_access: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub minter: Signer<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String, _mint: Pubkey)]
+pub struct UpdateNftMetadata<'info> {
+    #[account(
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: update authority checked against the collection pda
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), _mint.as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub metadata: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+// Public mint terms of a collection
+#[account]
+pub struct MintGate {
+    pub collection: Pubkey,
+    pub price: u64,
+    // Pubkey::default() for SOL
+    pub payment_mint: Pubkey,
+    // Minters allowed
+    pub access: AccessPolicy,
+    // No cap if 0
+    pub max_supply: u32,
+    pub minted: u32,
+}
+
+impl MintGate {
+    const SIZE: usize = 8   // discriminator
+    + 32 // collection
+    + 8 // price
+    + 32 // payment_mint
+    + AccessPolicy::SIZE // access
+    + 4 // max_supply
+    + 4; // minted
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+
+use crate::ErrorCode;
+
+// Only the program upgrade authority can create the config, and become its
+// first admin
+pub fn init_config(ctx: Context<InitConfig>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = ctx.accounts.admin.key();
+    config.paused = false;
+    Ok(())
+}
+
+pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+    ctx.accounts.config.paused = paused;
+    Ok(())
+}
+
+pub fn set_config_admin(ctx: Context<UpdateConfig>, admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.admin = admin;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(
+        init, payer = admin,
+        space = Config::SIZE,
+        seeds = [b"config".as_ref()],
+        bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut,
+        seeds = [b"config".as_ref()],
+        bump,
+        has_one = admin)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+// Program wide settings. While paused, instructions taking new payments or
+// minting are rejected, exits (delisting, returns, refunds) still go through
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    pub paused: bool,
+}
+
+impl Config {
+    const SIZE: usize = 8   // discriminator
+    + 32 // admin
+    + 1; // paused
+}
@@ -0,0 +1,322 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Approve, Mint, Revoke, Token, TokenAccount};
+
+use mpl_token_metadata::state::PREFIX;
+
+use crate::nft_utils::{freeze_delegated, thaw_delegated, TOKEN_METADATA_PROGRAM_ID};
+use crate::pnft::{is_programmable, PnftAccounts};
+use crate::time::now;
+use crate::token_interface;
+use crate::{ErrorCode, Nft};
+
+// Non-custodial mode: the nft stays in the owner wallet, with the program
+// delegate authority freezing it for the rental duration. Programmable nfts
+// are locked instead, through a utility delegate, their remaining accounts
+// being [token record, system program, sysvar instructions, authorization
+// rules program, authorization rules]
+
+pub fn delegate_nft<'info>(
+    ctx: Context<'_, '_, '_, 'info, DelegateNft<'info>>,
+    _mint: Pubkey,
+) -> Result<()> {
+    // Token-2022 nfts can't be delegated yet
+    token_interface::require_token_program(&ctx.accounts.nft_mint)?;
+    token_interface::require_token_program(&ctx.accounts.token_account)?;
+    let token_state = token_interface::token_account(&ctx.accounts.token_account)?;
+    if token_state.owner != ctx.accounts.owner.key()
+        || token_state.mint != _mint
+        || token_state.amount != 1
+    {
+        return err!(ErrorCode::InvalidDelegation);
+    }
+
+    if is_programmable(&ctx.accounts.metadata)? {
+        let token_account = ctx.accounts.token_account.to_account_info();
+        let nft_mint = ctx.accounts.nft_mint.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let pnft = PnftAccounts::new(
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.metadata,
+            &ctx.accounts.edition,
+            &nft_mint,
+            &token_account,
+            &token_program,
+            ctx.remaining_accounts,
+        )?;
+        pnft.delegate(&ctx.accounts.delegate_authority, &ctx.accounts.owner)?;
+    } else {
+        let cpi_accounts = Approve {
+            to: ctx.accounts.token_account.to_account_info(),
+            delegate: ctx.accounts.delegate_authority.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        };
+        token::approve(
+            CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+            1,
+        )?;
+    }
+    ctx.accounts.nft.token_account = ctx.accounts.token_account.key();
+    Ok(())
+}
+
+pub fn freeze_rented_nft<'info>(
+    ctx: Context<'_, '_, '_, 'info, FreezeRentedNft<'info>>,
+    _mint: Pubkey,
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let timestamp = now()?;
+    if !nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftNotRented);
+    }
+    let mut accounts = vec![
+        ctx.accounts.token_account.to_account_info(),
+        ctx.accounts.edition.to_account_info(),
+        ctx.accounts.nft_mint.to_account_info(),
+        ctx.accounts.delegate_authority.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.token_metadata_program.to_account_info(),
+        ctx.accounts.metadata.to_account_info(),
+    ];
+    accounts.extend_from_slice(ctx.remaining_accounts);
+    freeze_rented(nft, &accounts, ctx.program_id)
+}
+
+pub fn end_rental<'info>(
+    ctx: Context<'_, '_, '_, 'info, EndRental<'info>>,
+    _mint: Pubkey,
+) -> Result<()> {
+    let nft = &mut ctx.accounts.nft;
+    let token_account = &ctx.accounts.token_account;
+    let token_program = &ctx.accounts.token_program;
+
+    let timestamp = now()?;
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+
+    if is_programmable(&ctx.accounts.metadata)? {
+        let bump = *ctx.bumps.get("delegate_authority").unwrap();
+        let token_account_info = token_account.to_account_info();
+        let nft_mint_info = ctx.accounts.nft_mint.to_account_info();
+        let token_program_info = token_program.to_account_info();
+        let pnft = PnftAccounts::new(
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.metadata,
+            &ctx.accounts.edition,
+            &nft_mint_info,
+            &token_account_info,
+            &token_program_info,
+            ctx.remaining_accounts,
+        )?;
+        if pnft.is_locked()? {
+            pnft.unlock(
+                &ctx.accounts.delegate_authority,
+                &[&[b"delegate".as_ref(), &[bump]]],
+            )?;
+        }
+        pnft.revoke(&ctx.accounts.delegate_authority, &ctx.accounts.owner)?;
+        nft.token_account = Pubkey::default();
+        return Ok(());
+    }
+
+    if token_account.is_frozen() {
+        let bump = *ctx.bumps.get("delegate_authority").unwrap();
+        thaw_delegated(
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.delegate_authority,
+            &token_account.to_account_info(),
+            &ctx.accounts.edition,
+            &ctx.accounts.nft_mint.to_account_info(),
+            &token_program.to_account_info(),
+            &[&[b"delegate".as_ref(), &[bump]]],
+        )?;
+    }
+    let cpi_accounts = Revoke {
+        source: token_account.to_account_info(),
+        authority: ctx.accounts.owner.to_account_info(),
+    };
+    token::revoke(CpiContext::new(
+        token_program.to_account_info(),
+        cpi_accounts,
+    ))?;
+    nft.token_account = Pubkey::default();
+    Ok(())
+}
+
+// Count of the freeze_rented accounts starting `accounts`, programmable nfts
+// adding their 5 accounts to the 7 common ones
+pub fn freeze_accounts_len(accounts: &[AccountInfo]) -> Result<usize> {
+    let metadata = accounts.get(6).ok_or(ErrorCode::InvalidDelegation)?;
+    Ok(if is_programmable(metadata)? { 12 } else { 7 })
+}
+
+// Freezes the delegated token account of a rented non-custodial nft, or locks
+// a programmable one, if not already.
+// Accounts: [token account, edition, mint, delegate authority, token program,
+// token metadata program, metadata], followed by the programmable nft accounts
+pub fn freeze_rented<'info>(
+    nft: &Nft,
+    accounts: &[AccountInfo<'info>],
+    program_id: &Pubkey,
+) -> Result<()> {
+    if accounts.len() < 7 {
+        return err!(ErrorCode::InvalidDelegation);
+    }
+    token_interface::require_token_program(&accounts[0])?;
+    let token_account = Account::<TokenAccount>::try_from(&accounts[0])?;
+    let delegate_authority = &accounts[3];
+    let (delegate_pda, bump) = Pubkey::find_program_address(&[b"delegate".as_ref()], program_id);
+
+    // The owner must still hold the nft, delegated to the program
+    if token_account.key() != nft.token_account
+        || token_account.owner != nft.owner
+        || token_account.mint != nft.mint
+        || token_account.amount != 1
+        || token_account.delegate != Some(delegate_pda).into()
+        || delegate_authority.key() != delegate_pda
+    {
+        return err!(ErrorCode::InvalidDelegation);
+    }
+    if accounts[4].key() != token::ID
+        || accounts[5].key() != TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap()
+    {
+        return err!(ErrorCode::InvalidDelegation);
+    }
+    let (metadata_key, _) = Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            accounts[5].key.as_ref(),
+            nft.mint.as_ref(),
+        ],
+        accounts[5].key,
+    );
+    if accounts[6].key() != metadata_key {
+        return err!(ErrorCode::InvalidDelegation);
+    }
+    if is_programmable(&accounts[6])? {
+        let pnft = PnftAccounts::new(
+            &accounts[5],
+            &accounts[6],
+            &accounts[1],
+            &accounts[2],
+            &accounts[0],
+            &accounts[4],
+            &accounts[7..],
+        )?;
+        if pnft.is_locked()? {
+            return Ok(());
+        }
+        return pnft.lock(delegate_authority, &[&[b"delegate".as_ref(), &[bump]]]);
+    }
+    if token_account.is_frozen() {
+        return Ok(());
+    }
+    freeze_delegated(
+        &accounts[5],
+        delegate_authority,
+        &accounts[0],
+        &accounts[1],
+        &accounts[2],
+        &accounts[4],
+        &[&[b"delegate".as_ref(), &[bump]]],
+    )
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct DelegateNft<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    // Owner token account, of the Token program
+    /// CHECK: parsed by the token interface
+    #[account(mut)]
+    pub token_account: AccountInfo<'info>,
+    /// CHECK: pda delegated the nft
+    #[account(
+        seeds = [b"delegate".as_ref()],
+        bump)]
+    pub delegate_authority: UncheckedAccount<'info>,
+    /// CHECK: master edition, checked by the token metadata program
+    pub edition: UncheckedAccount<'info>,
+    /// CHECK: checked against the token program
+    #[account(address = _mint)]
+    pub nft_mint: AccountInfo<'info>,
+    /// CHECK: metadata of the nft, read for its token standard
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), _mint.as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub metadata: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct FreezeRentedNft<'info> {
+    #[account(
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.token_account)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: master edition, checked by the token metadata program
+    pub edition: UncheckedAccount<'info>,
+    #[account(address = _mint)]
+    pub nft_mint: Account<'info, Mint>,
+    /// CHECK: pda delegated the nft
+    #[account(
+        seeds = [b"delegate".as_ref()],
+        bump)]
+    pub delegate_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: metadata of the nft, read for its token standard
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), _mint.as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub metadata: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct EndRental<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.token_account)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: master edition, checked by the token metadata program
+    pub edition: UncheckedAccount<'info>,
+    #[account(address = _mint)]
+    pub nft_mint: Account<'info, Mint>,
+    /// CHECK: pda delegated the nft
+    #[account(
+        seeds = [b"delegate".as_ref()],
+        bump)]
+    pub delegate_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    /// CHECK: metadata of the nft, read for its token standard
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), _mint.as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub metadata: UncheckedAccount<'info>,
+}
@@ -1,6 +1,66 @@
 use anchor_lang::prelude::*;
+mod access;
+mod auction;
+mod bookings;
+mod cnft;
+mod collection_mint;
+mod config;
+mod delegation;
+mod math;
 mod nft_utils;
-use nft_utils::{mint_nft as _mint_nft, verify_nft as _verify_nft, *}; // important
+mod offers;
+mod pnft;
+mod records;
+mod rental_pass;
+mod rented_metadata;
+mod time;
+mod token_interface;
+mod vault;
+use access::{
+    add_collection_access_entry as _add_collection_access_entry,
+    add_nft_access_entry as _add_nft_access_entry,
+    remove_collection_access_entry as _remove_collection_access_entry,
+    remove_nft_access_entry as _remove_nft_access_entry,
+    set_collection_access as _set_collection_access, set_nft_access as _set_nft_access, *,
+};
+use auction::{
+    cancel_rental_auction as _cancel_rental_auction, place_bid as _place_bid,
+    settle_rental_auction as _settle_rental_auction, start_rental_auction as _start_rental_auction,
+    *,
+};
+use bookings::{
+    book_nft as _book_nft, cancel_booking as _cancel_booking, close_bookings as _close_bookings,
+    init_bookings as _init_bookings, update_bookings as _update_bookings, *,
+};
+use cnft::{
+    create_cnft as _create_cnft, delete_cnft as _delete_cnft, release_cnft as _release_cnft,
+    rent_cnft as _rent_cnft, *,
+};
+use collection_mint::{
+    create_collection_mint as _create_collection_mint, gated_mint as _gated_mint,
+    init_mint_gate as _init_mint_gate, mint_and_list as _mint_and_list,
+    update_mint_gate as _update_mint_gate, update_nft_metadata as _update_nft_metadata, *,
+};
+use config::{
+    init_config as _init_config, set_config_admin as _set_config_admin, set_paused as _set_paused,
+    *,
+};
+use delegation::{
+    delegate_nft as _delegate_nft, end_rental as _end_rental,
+    freeze_rented_nft as _freeze_rented_nft, *,
+};
+use math::*;
+use nft_utils::{
+    mint_nft as _mint_nft, print_edition as _print_edition, verify_nft as _verify_nft, *,
+}; // important
+use offers::{
+    accept_rental_offer as _accept_rental_offer, cancel_rental_offer as _cancel_rental_offer,
+    create_rental_offer as _create_rental_offer, *,
+};
+use records::{close_rental_record as _close_rental_record, *};
+use rental_pass::{burn_expired_pass as _burn_expired_pass, *};
+use rented_metadata::{set_rented_metadata as _set_rented_metadata, *};
+use time::*;
 
 declare_id!("6R4xELxAKseqmCxS9Vf7VQya4FoEcY4roC7GRYyEf6Hx");
 
@@ -32,9 +92,50 @@ pub mod rental {
         collection.website_url = website_url;
         collection.royalties_percent = royalties_percent;
         collection.app_count = 0;
+        collection.listing_count = 0;
+        collection.total_rentals = 0;
+        collection.volume = 0;
+        collection.active_rentals = 0;
+        collection.policy = RentalPolicy::default();
+        collection.access = AccessPolicy::Open;
+        collection.collection_mint = Pubkey::default();
+        collection.authority = ctx.accounts.authority.key();
+        collection.rented_metadata = false;
         Ok(())
     }
 
+    pub fn update_collection_policy(
+        ctx: Context<UpdateCollection>,
+        _symbol: String,
+        policy: RentalPolicy,
+    ) -> Result<()> {
+        // Check args
+        require!(
+            policy.payment_mints.len() <= RentalPolicy::PAYMENT_MINTS_MAX_LEN,
+            ErrorCode::TooManyPaymentMints
+        );
+        require!(
+            policy.min_price <= policy.max_price,
+            ErrorCode::InvalidRentalPolicy
+        );
+        // Default listing terms stay within the bounds
+        let (rental_max_days, rental_price, payment_mint) = policy.default_listing();
+        policy
+            .check_listing(rental_max_days, rental_price, &payment_mint)
+            .map_err(|_| error!(ErrorCode::InvalidRentalPolicy))?;
+        let collection = &mut ctx.accounts.collection;
+        collection.policy = policy;
+        Ok(())
+    }
+
+    pub fn set_rented_metadata(
+        ctx: Context<SetRentedMetadata>,
+        _symbol: String,
+        enabled: bool,
+    ) -> Result<()> {
+        _set_rented_metadata(ctx, _symbol, enabled)
+    }
+
     pub fn update_collection(
         ctx: Context<UpdateCollection>,
         _symbol: String,
@@ -79,7 +180,10 @@ pub mod rental {
         collection_app.name = name;
         collection_app.image_url = image_url;
         collection_app.app_url = app_url;
-        collection.app_count += 1;
+        collection.app_count = collection
+            .app_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
         Ok(())
     }
 
@@ -105,7 +209,21 @@ pub mod rental {
 
     pub fn remove_collection_app(ctx: Context<RemoveCollectionApp>, _symbol: String) -> Result<()> {
         let collection = &mut ctx.accounts.collection;
-        collection.app_count -= 1;
+        collection.app_count = collection
+            .app_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn close_collection(ctx: Context<CloseCollection>, _symbol: String) -> Result<()> {
+        // The collection can only be closed once nothing references it anymore
+        let collection = &ctx.accounts.collection;
+        require!(collection.app_count == 0, ErrorCode::CollectionHasApps);
+        require!(
+            collection.listing_count == 0,
+            ErrorCode::CollectionHasListings
+        );
         Ok(())
     }
 
@@ -116,19 +234,31 @@ pub mod rental {
         rental_max_days: u32,
         rental_price: u64,
         rental_enabled: bool,
+        payment_mint: Pubkey,
     ) -> Result<()> {
         // TODO: verify Nft ownership
         let nft = &mut ctx.accounts.nft;
         let owner = &mut ctx.accounts.owner;
+        let collection_account = &mut ctx.accounts.collection;
 
-        nft.mint = mint;
-        nft.collection = collection;
-        nft.owner = owner.key();
-        nft.rental_max_days = rental_max_days;
-        nft.rental_price = rental_price;
-        nft.rental_count = 0;
-        nft.rental_enabled = rental_enabled;
-        nft.rented_until = 0;
+        // Check listing against the collection policy
+        collection_account
+            .policy
+            .check_listing(rental_max_days, rental_price, &payment_mint)?;
+
+        nft.list(
+            mint,
+            collection,
+            owner.key(),
+            rental_max_days,
+            rental_price,
+            rental_enabled,
+            payment_mint,
+        );
+        collection_account.listing_count = collection_account
+            .listing_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         Ok(())
     }
@@ -139,23 +269,97 @@ pub mod rental {
         rental_max_days: u32,
         rental_price: u64,
         rental_enabled: bool,
+        payment_mint: Pubkey,
     ) -> Result<()> {
         //
         let nft = &mut ctx.accounts.nft;
+        let collection = &ctx.accounts.collection;
+
+        // Check listing against the collection policy
+        collection
+            .policy
+            .check_listing(rental_max_days, rental_price, &payment_mint)?;
+        // Booking activations find their accounts after the payment ones, the
+        // payment mint is kept while reservations are pending
+        if payment_mint != nft.payment_mint && has_reservations(&ctx.accounts.bookings)? {
+            return err!(ErrorCode::NftBooked);
+        }
 
         nft.rental_max_days = rental_max_days;
         nft.rental_price = rental_price;
         nft.rental_enabled = rental_enabled;
+        nft.payment_mint = payment_mint;
+
+        Ok(())
+    }
+
+    pub fn delete_nft(ctx: Context<DeleteNft>, _mint: Pubkey) -> Result<()> {
+        let nft = &ctx.accounts.nft;
+        let collection = &mut ctx.accounts.collection;
+        let bookings_info = ctx.accounts.bookings.to_account_info();
+
+        // A rented nft can't be delisted
+        let timestamp = now()?;
+        if nft.is_rented(timestamp) {
+            return err!(ErrorCode::NftRented);
+        }
+        // Nor one with paid reservations
+        if !bookings_info.data_is_empty() {
+            return err!(ErrorCode::NftBooked);
+        }
+        // Nor one with an open auction, escrowing bids
+        if !ctx.accounts.auction.data_is_empty() {
+            return err!(ErrorCode::AuctionOpen);
+        }
+        // Nor one still delegated, the owner ends the delegation first
+        if nft.token_account != Pubkey::default() {
+            return err!(ErrorCode::NftDelegated);
+        }
+        // Compressed nfts are delisted by delete_cnft, returning the leaf
+        if !ctx.accounts.cnft.data_is_empty() {
+            return err!(ErrorCode::CompressedListing);
+        }
 
+        collection.listing_count = collection
+            .listing_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if nft.renter != Pubkey::default() {
+            collection.active_rentals = collection
+                .active_rentals
+                .checked_sub(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+            terminate_rental_record(
+                &ctx.accounts.record,
+                TerminationReason::Delisted,
+                ctx.program_id,
+            )?;
+            sync_rented_metadata(
+                collection,
+                &ctx.accounts.metadata,
+                &ctx.accounts.token_metadata_program,
+                &nft.mint,
+                false,
+                ctx.program_id,
+            )?;
+        }
         Ok(())
     }
 
-    pub fn rent_nft(
-        ctx: Context<RentNft>,
+    // Rents the nft, minting a rental pass to the renter when `pass_uri` is set.
+    // Remaining accounts: the payment accounts of spl payment mints followed by
+    // the bookings vault, then the freeze_rented accounts of delegated nfts, then
+    // the rental pass accounts
+    #[allow(clippy::too_many_arguments)]
+    pub fn rent_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, RentNft<'info>>,
         _mint: Pubkey,
         symbol: String,
         autority: Pubkey,
         days: u32,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+        pass_uri: Option<String>,
     ) -> Result<()> {
         // TODO: Enable rental extension
         let nft = &mut ctx.accounts.nft;
@@ -163,71 +367,706 @@ pub mod rental {
         let renter = &mut ctx.accounts.renter;
         let system_program = &ctx.accounts.system_program;
         let collection = &ctx.accounts.collection;
+        let record = &mut ctx.accounts.record;
 
         // Control collection key
         let (pda, _bump_seed) = Pubkey::find_program_address(
             &[b"collection".as_ref(), symbol.as_ref(), autority.as_ref()],
             ctx.program_id,
         );
-        assert!(pda == collection.key());
-
-        // Parse collection
-        let info = collection.to_account_info();
-        let mut data: &[u8] = &info.try_borrow_data()?;
-        let deserialized = Collection::try_deserialize(&mut data);
+        if pda != collection.key() {
+            return err!(ErrorCode::AccountMismatch);
+        }
 
-        if let Ok(collection) = deserialized {
-            msg!("Deserialized: ${:?}", collection.name);
-        } else {
-            msg!("Collection doesn't exist");
+        if collection.key() != nft.collection {
+            return err!(ErrorCode::InvalidCollection);
         }
 
-        let clock: Clock = Clock::get().unwrap();
-        let timestamp = clock.unix_timestamp;
+        // Parse collection
+        let mut collection = Account::<Collection>::try_from(&collection.to_account_info())?;
+
+        let timestamp = now()?;
 
         // Check nft rental validity
         if owner.key() != nft.owner {
             return err!(ErrorCode::InvalidOwner);
         }
-        if nft.rented_until > timestamp {
+        if nft.is_rented(timestamp) {
             return err!(ErrorCode::NftRented);
         }
-        if !nft.rental_enabled {
-            return err!(ErrorCode::NftNotListed);
+
+        // Check renter access
+        check_access(
+            &nft.access,
+            &nft.key(),
+            &renter.key(),
+            &ctx.accounts.nft_access,
+            &nft_proof,
+            ctx.program_id,
+        )?;
+        check_access(
+            &collection.access,
+            &nft.collection,
+            &renter.key(),
+            &ctx.accounts.collection_access,
+            &collection_proof,
+            ctx.program_id,
+        )?;
+
+        // Delegated nfts are frozen in the owner wallet, through the accounts
+        // following the payment accounts
+        let delegation_accounts = if nft.payment_mint == Pubkey::default() {
+            ctx.remaining_accounts
+        } else {
+            ctx.remaining_accounts.get(5..).unwrap_or(&[])
+        };
+        let pass_accounts = if nft.token_account == Pubkey::default() {
+            delegation_accounts
+        } else {
+            let len = freeze_accounts_len(delegation_accounts)?;
+            delegation_accounts.get(len..).unwrap_or(&[])
+        };
+
+        // Parse bookings, if any
+        let bookings_info = ctx.accounts.bookings.to_account_info();
+        let mut bookings = if bookings_info.data_is_empty() {
+            None
+        } else {
+            Some(Account::<Bookings>::try_from(&bookings_info)?)
+        };
+        if let Some(bookings) = bookings.as_mut() {
+            // Reservations that were never activated are due to the owner
+            let expired = bookings.prune_expired(timestamp);
+            pay_bookings_escrow(
+                &bookings_info,
+                owner,
+                nft,
+                ctx.remaining_accounts,
+                expired,
+                ctx.program_id,
+            )?;
+
+            // Activate the renter's reservation, already paid in escrow
+            if let Some(reservation) = bookings.take_due(&renter.key(), timestamp) {
+                pay_bookings_escrow(
+                    &bookings_info,
+                    owner,
+                    nft,
+                    ctx.remaining_accounts,
+                    reservation.amount,
+                    ctx.program_id,
+                )?;
+                bookings.exit(ctx.program_id)?;
+                let days = ((reservation.end - reservation.start) / DAY_S) as u32;
+                nft.start_rental(
+                    &mut collection,
+                    renter.key(),
+                    reservation.end,
+                    days,
+                    reservation.amount,
+                )?;
+                record.start(nft, renter.key(), reservation.start, reservation.amount);
+                sync_rented_metadata(
+                    &collection,
+                    &ctx.accounts.metadata,
+                    &ctx.accounts.token_metadata_program,
+                    &nft.mint,
+                    true,
+                    ctx.program_id,
+                )?;
+                collection.exit(ctx.program_id)?;
+                if nft.token_account != Pubkey::default() {
+                    freeze_rented(nft, delegation_accounts, ctx.program_id)?;
+                }
+                if let Some(base_uri) = pass_uri {
+                    mint_rental_pass(
+                        nft,
+                        record,
+                        &renter.to_account_info(),
+                        &ctx.accounts.token_metadata_program,
+                        &system_program.to_account_info(),
+                        pass_accounts,
+                        base_uri,
+                        ctx.program_id,
+                    )?;
+                }
+                return Ok(());
+            }
+            bookings.exit(ctx.program_id)?;
         }
-        if days > nft.rental_max_days {
-            return err!(ErrorCode::InvalidRentalDuration);
+
+        nft.check_rentable(&collection.policy, days)?;
+        let rented_until = rental_end(timestamp, days)?;
+        if let Some(bookings) = bookings.as_ref() {
+            if bookings.overlaps(timestamp, rented_until) {
+                return err!(ErrorCode::NftBooked);
+            }
         }
 
         // Transfer funds
-        transfer_funds(
+        let price = rental_amount(nft.rental_price, days)?;
+        transfer_payment(
+            &nft.payment_mint,
             &renter,
             &owner,
             &system_program.to_account_info(),
-            nft.rental_price * days as u64,
+            ctx.remaining_accounts,
+            price,
         )?;
 
         // Rent nft
-        nft.renter = renter.key();
-        nft.rented_until = timestamp + DAY_S * days as i64;
+        nft.start_rental(&mut collection, renter.key(), rented_until, days, price)?;
+        record.start(nft, renter.key(), timestamp, price);
+        sync_rented_metadata(
+            &collection,
+            &ctx.accounts.metadata,
+            &ctx.accounts.token_metadata_program,
+            &nft.mint,
+            true,
+            ctx.program_id,
+        )?;
+        collection.exit(ctx.program_id)?;
+        if nft.token_account != Pubkey::default() {
+            freeze_rented(nft, delegation_accounts, ctx.program_id)?;
+        }
+        if let Some(base_uri) = pass_uri {
+            mint_rental_pass(
+                nft,
+                record,
+                &renter.to_account_info(),
+                &ctx.accounts.token_metadata_program,
+                &system_program.to_account_info(),
+                pass_accounts,
+                base_uri,
+                ctx.program_id,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Rents every nft of the bundle for the same duration, from a single owner.
+    // Remaining accounts: [nft, collection, bookings, nft access, collection access,
+    // rental record, metadata] for each of the `count` nfts, followed by the payment accounts
+    // of spl payment mints, then the freeze_rented accounts of each delegated nft, in bundle
+    // order. `proofs` holds the nft & collection access proofs of each nft.
+    // Reservations are not activated here: an nft with a reservation overlapping the bundle
+    // rental, the renter's own included, is rejected and rented through rent_nft instead
+    pub fn rent_bundle<'info>(
+        ctx: Context<'_, '_, '_, 'info, RentBundle<'info>>,
+        count: u8,
+        days: u32,
+        proofs: Vec<Vec<[u8; 32]>>,
+    ) -> Result<()> {
+        let owner = &ctx.accounts.owner;
+        let renter = &ctx.accounts.renter;
+        let system_program = &ctx.accounts.system_program;
+
+        let count = count as usize;
+        if count == 0 || ctx.remaining_accounts.len() < 7 * count || proofs.len() != 2 * count {
+            return err!(ErrorCode::InvalidBundle);
+        }
+        let (bundle_accounts, payment_accounts) = ctx.remaining_accounts.split_at(7 * count);
+
+        let timestamp = now()?;
+        let rented_until = rental_end(timestamp, days)?;
+
+        // Check every nft with the same rules as rent_nft
+        let mut nfts: Vec<(Account<Nft>, usize)> = Vec::with_capacity(count);
+        // Distinct collections of the bundle, updated once
+        let mut collections: Vec<Account<Collection>> = vec![];
+        let mut price: u64 = 0;
+        for (accounts, proofs) in bundle_accounts.chunks(7).zip(proofs.chunks(2)) {
+            let nft = Account::<Nft>::try_from(&accounts[0])?;
+            let index = match collections
+                .iter()
+                .position(|c| c.key() == accounts[1].key())
+            {
+                Some(index) => index,
+                None => {
+                    collections.push(Account::<Collection>::try_from(&accounts[1])?);
+                    collections.len() - 1
+                }
+            };
+            let collection = &collections[index];
+            let bookings_info = &accounts[2];
+
+            if nfts.iter().any(|(other, _)| other.key() == nft.key()) {
+                return err!(ErrorCode::InvalidBundle);
+            }
+            if collection.key() != nft.collection {
+                return err!(ErrorCode::InvalidCollection);
+            }
+            let (bookings_pda, _bump_seed) = Pubkey::find_program_address(
+                &[b"bookings".as_ref(), nft.mint.as_ref()],
+                ctx.program_id,
+            );
+            if bookings_pda != bookings_info.key() {
+                return err!(ErrorCode::InvalidBundle);
+            }
+            if owner.key() != nft.owner {
+                return err!(ErrorCode::InvalidOwner);
+            }
+            if nft.is_rented(timestamp) {
+                return err!(ErrorCode::NftRented);
+            }
+            check_access(
+                &nft.access,
+                &nft.key(),
+                &renter.key(),
+                &accounts[3],
+                &proofs[0],
+                ctx.program_id,
+            )?;
+            check_access(
+                &collection.access,
+                &collection.key(),
+                &renter.key(),
+                &accounts[4],
+                &proofs[1],
+                ctx.program_id,
+            )?;
+            nft.check_rentable(&collection.policy, days)?;
+            check_not_booked(bookings_info, timestamp, rented_until)?;
+            // A single transfer requires a single payment mint
+            if nft.payment_mint
+                != nfts
+                    .first()
+                    .map_or(nft.payment_mint, |(n, _)| n.payment_mint)
+            {
+                return err!(ErrorCode::InvalidPaymentMint);
+            }
+
+            price = checked_inc(price, rental_amount(nft.rental_price, days)?)?;
+            nfts.push((nft, index));
+        }
+
+        // Transfer funds
+        transfer_payment(
+            &nfts[0].0.payment_mint,
+            renter,
+            owner,
+            &system_program.to_account_info(),
+            payment_accounts,
+            price,
+        )?;
+
+        // Delegated nfts are frozen in the owner wallet, through the accounts
+        // following the payment accounts
+        let mut delegation_accounts = if nfts[0].0.payment_mint == Pubkey::default() {
+            payment_accounts
+        } else {
+            payment_accounts.get(4..).unwrap_or(&[])
+        };
+
+        // Rent nfts
+        for ((nft, index), accounts) in nfts.iter_mut().zip(bundle_accounts.chunks(7)) {
+            let price = rental_amount(nft.rental_price, days)?;
+            nft.start_rental(
+                &mut collections[*index],
+                renter.key(),
+                rented_until,
+                days,
+                price,
+            )?;
+            create_rental_record(
+                &accounts[5],
+                renter,
+                &system_program.to_account_info(),
+                nft,
+                timestamp,
+                price,
+                ctx.program_id,
+            )?;
+            sync_rented_metadata(
+                &collections[*index],
+                &accounts[6],
+                &ctx.accounts.token_metadata_program,
+                &nft.mint,
+                true,
+                ctx.program_id,
+            )?;
+            if nft.token_account != Pubkey::default() {
+                let len = freeze_accounts_len(delegation_accounts)?;
+                let freeze_accounts = delegation_accounts
+                    .get(..len)
+                    .ok_or(ErrorCode::InvalidDelegation)?;
+                freeze_rented(nft, freeze_accounts, ctx.program_id)?;
+                delegation_accounts = &delegation_accounts[len..];
+            }
+            nft.exit(ctx.program_id)?;
+        }
+        for collection in collections.iter() {
+            collection.exit(ctx.program_id)?;
+        }
 
         Ok(())
     }
 
     // Lib functions
-    pub fn mint_nft(
-        ctx: Context<MintNFT>,
-        creator_key: Pubkey,
+    #[allow(clippy::too_many_arguments)]
+    pub fn mint_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintNFT<'info>>,
         uri: String,
         title: String,
         symbol: String,
+        creators: Vec<CreatorShare>,
+        seller_fee_basis_points: u16,
+        collection_mint: Option<Pubkey>,
+        max_supply: Option<u64>,
     ) -> Result<()> {
-        _mint_nft(ctx, creator_key, uri, title, symbol)
+        _mint_nft(
+            ctx,
+            uri,
+            title,
+            symbol,
+            creators,
+            seller_fee_basis_points,
+            collection_mint,
+            max_supply,
+        )
+    }
+
+    pub fn print_edition(ctx: Context<PrintEdition>, edition: u64) -> Result<()> {
+        _print_edition(ctx, edition)
     }
 
     pub fn verify_nft(ctx: Context<VerifyNFT>) -> Result<()> {
         _verify_nft(ctx)
     }
+
+    // Bookings
+    pub fn init_bookings(
+        ctx: Context<InitBookings>,
+        mint: Pubkey,
+        cancel_notice_s: i64,
+        cancel_refund_percent: u8,
+    ) -> Result<()> {
+        _init_bookings(ctx, mint, cancel_notice_s, cancel_refund_percent)
+    }
+
+    pub fn update_bookings(
+        ctx: Context<UpdateBookings>,
+        cancel_notice_s: i64,
+        cancel_refund_percent: u8,
+    ) -> Result<()> {
+        _update_bookings(ctx, cancel_notice_s, cancel_refund_percent)
+    }
+
+    pub fn close_bookings(ctx: Context<CloseBookings>) -> Result<()> {
+        _close_bookings(ctx)
+    }
+
+    pub fn book_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, BookNft<'info>>,
+        start: i64,
+        days: u32,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _book_nft(ctx, start, days, nft_proof, collection_proof)
+    }
+
+    pub fn cancel_booking<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelBooking<'info>>,
+        start: i64,
+    ) -> Result<()> {
+        _cancel_booking(ctx, start)
+    }
+
+    // Auctions
+    pub fn start_rental_auction(
+        ctx: Context<StartRentalAuction>,
+        mint: Pubkey,
+        days: u32,
+        min_bid: u64,
+        bidding_end: i64,
+    ) -> Result<()> {
+        _start_rental_auction(ctx, mint, days, min_bid, bidding_end)
+    }
+
+    pub fn place_bid<'info>(
+        ctx: Context<'_, '_, '_, 'info, PlaceBid<'info>>,
+        amount: u64,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _place_bid(ctx, amount, nft_proof, collection_proof)
+    }
+
+    pub fn settle_rental_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleRentalAuction<'info>>,
+    ) -> Result<()> {
+        _settle_rental_auction(ctx)
+    }
+
+    pub fn cancel_rental_auction<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelRentalAuction<'info>>,
+    ) -> Result<()> {
+        _cancel_rental_auction(ctx)
+    }
+
+    // Offers
+    pub fn create_rental_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateRentalOffer<'info>>,
+        mint: Pubkey,
+        price: u64,
+        days: u32,
+        expires_at: i64,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _create_rental_offer(
+            ctx,
+            mint,
+            price,
+            days,
+            expires_at,
+            nft_proof,
+            collection_proof,
+        )
+    }
+
+    pub fn accept_rental_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, AcceptRentalOffer<'info>>,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _accept_rental_offer(ctx, nft_proof, collection_proof)
+    }
+
+    pub fn cancel_rental_offer<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelRentalOffer<'info>>,
+    ) -> Result<()> {
+        _cancel_rental_offer(ctx)
+    }
+
+    // Access
+    pub fn set_nft_access(
+        ctx: Context<SetNftAccess>,
+        mint: Pubkey,
+        access: AccessPolicy,
+    ) -> Result<()> {
+        _set_nft_access(ctx, mint, access)
+    }
+
+    pub fn set_collection_access(
+        ctx: Context<SetCollectionAccess>,
+        symbol: String,
+        access: AccessPolicy,
+    ) -> Result<()> {
+        _set_collection_access(ctx, symbol, access)
+    }
+
+    pub fn add_nft_access_entry(
+        ctx: Context<AddNftAccessEntry>,
+        mint: Pubkey,
+        wallet: Pubkey,
+    ) -> Result<()> {
+        _add_nft_access_entry(ctx, mint, wallet)
+    }
+
+    pub fn add_collection_access_entry(
+        ctx: Context<AddCollectionAccessEntry>,
+        symbol: String,
+        wallet: Pubkey,
+    ) -> Result<()> {
+        _add_collection_access_entry(ctx, symbol, wallet)
+    }
+
+    pub fn remove_nft_access_entry(ctx: Context<RemoveNftAccessEntry>, mint: Pubkey) -> Result<()> {
+        _remove_nft_access_entry(ctx, mint)
+    }
+
+    pub fn remove_collection_access_entry(
+        ctx: Context<RemoveCollectionAccessEntry>,
+        symbol: String,
+    ) -> Result<()> {
+        _remove_collection_access_entry(ctx, symbol)
+    }
+
+    // Non-custodial rentals
+    pub fn delegate_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, DelegateNft<'info>>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        _delegate_nft(ctx, mint)
+    }
+
+    pub fn freeze_rented_nft<'info>(
+        ctx: Context<'_, '_, '_, 'info, FreezeRentedNft<'info>>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        _freeze_rented_nft(ctx, mint)
+    }
+
+    pub fn end_rental<'info>(
+        ctx: Context<'_, '_, '_, 'info, EndRental<'info>>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        _end_rental(ctx, mint)
+    }
+
+    // Releases an expired rental from the collection active rentals, anyone can crank it
+    pub fn release_rental(ctx: Context<ReleaseRental>, _mint: Pubkey) -> Result<()> {
+        let nft = &mut ctx.accounts.nft;
+        let collection = &mut ctx.accounts.collection;
+
+        let timestamp = now()?;
+        if nft.is_rented(timestamp) {
+            return err!(ErrorCode::NftRented);
+        }
+        if nft.renter == Pubkey::default() {
+            return err!(ErrorCode::NftNotRented);
+        }
+        nft.renter = Pubkey::default();
+        collection.active_rentals = collection
+            .active_rentals
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        terminate_rental_record(
+            &ctx.accounts.record,
+            TerminationReason::Expired,
+            ctx.program_id,
+        )?;
+        sync_rented_metadata(
+            collection,
+            &ctx.accounts.metadata,
+            &ctx.accounts.token_metadata_program,
+            &nft.mint,
+            false,
+            ctx.program_id,
+        )
+    }
+
+    // Rental records
+    pub fn close_rental_record(ctx: Context<CloseRentalRecord>) -> Result<()> {
+        _close_rental_record(ctx)
+    }
+
+    // Rental passes
+    pub fn burn_expired_pass(ctx: Context<BurnExpiredPass>) -> Result<()> {
+        _burn_expired_pass(ctx)
+    }
+
+    // Config
+    pub fn init_config(ctx: Context<InitConfig>) -> Result<()> {
+        _init_config(ctx)
+    }
+
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        _set_paused(ctx, paused)
+    }
+
+    pub fn set_config_admin(ctx: Context<UpdateConfig>, admin: Pubkey) -> Result<()> {
+        _set_config_admin(ctx, admin)
+    }
+
+    // Collection mints
+    pub fn create_collection_mint(
+        ctx: Context<CreateCollectionMint>,
+        _symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        _create_collection_mint(ctx, _symbol, uri)
+    }
+
+    pub fn mint_and_list(
+        ctx: Context<MintAndList>,
+        _symbol: String,
+        uri: String,
+        title: String,
+    ) -> Result<()> {
+        _mint_and_list(ctx, _symbol, uri, title)
+    }
+
+    pub fn init_mint_gate(
+        ctx: Context<InitMintGate>,
+        _symbol: String,
+        price: u64,
+        payment_mint: Pubkey,
+        access: AccessPolicy,
+        max_supply: u32,
+    ) -> Result<()> {
+        _init_mint_gate(ctx, _symbol, price, payment_mint, access, max_supply)
+    }
+
+    pub fn update_mint_gate(
+        ctx: Context<UpdateMintGate>,
+        _symbol: String,
+        price: u64,
+        payment_mint: Pubkey,
+        access: AccessPolicy,
+        max_supply: u32,
+    ) -> Result<()> {
+        _update_mint_gate(ctx, _symbol, price, payment_mint, access, max_supply)
+    }
+
+    pub fn gated_mint(
+        ctx: Context<GatedMint>,
+        _symbol: String,
+        uri: String,
+        title: String,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _gated_mint(ctx, _symbol, uri, title, proof)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_nft_metadata(
+        ctx: Context<UpdateNftMetadata>,
+        _symbol: String,
+        _mint: Pubkey,
+        name: Option<String>,
+        uri: Option<String>,
+        creators: Option<Vec<CreatorShare>>,
+        is_mutable: Option<bool>,
+    ) -> Result<()> {
+        _update_nft_metadata(ctx, _symbol, _mint, name, uri, creators, is_mutable)
+    }
+
+    // Compressed nfts
+    pub fn create_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateCnft<'info>>,
+        asset_id: Pubkey,
+        leaf: LeafArgs,
+        rental_max_days: u32,
+        rental_price: u64,
+        rental_enabled: bool,
+        payment_mint: Pubkey,
+    ) -> Result<()> {
+        _create_cnft(
+            ctx,
+            asset_id,
+            leaf,
+            rental_max_days,
+            rental_price,
+            rental_enabled,
+            payment_mint,
+        )
+    }
+
+    pub fn rent_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, RentCnft<'info>>,
+        asset_id: Pubkey,
+        days: u32,
+        nft_proof: Vec<[u8; 32]>,
+        collection_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        _rent_cnft(ctx, asset_id, days, nft_proof, collection_proof)
+    }
+
+    pub fn release_cnft(ctx: Context<ReleaseCnft>, asset_id: Pubkey) -> Result<()> {
+        _release_cnft(ctx, asset_id)
+    }
+
+    pub fn delete_cnft<'info>(
+        ctx: Context<'_, '_, '_, 'info, DeleteCnft<'info>>,
+        asset_id: Pubkey,
+        root: [u8; 32],
+    ) -> Result<()> {
+        _delete_cnft(ctx, asset_id, root)
+    }
 }
 
 #[derive(Accounts)]
@@ -293,7 +1132,19 @@ pub struct RemoveCollectionApp<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(mint: Pubkey)]
+#[instruction(_symbol: String)]
+pub struct CloseCollection<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump,
+        close = authority)]
+    pub collection: Account<'info, Collection>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey, collection_key: Pubkey)]
 pub struct CreateNft<'info> {
     #[account(
         init, payer = owner,
@@ -301,9 +1152,16 @@ pub struct CreateNft<'info> {
         seeds = [b"nft".as_ref(), mint.as_ref()],
         bump)]
     pub nft: Account<'info, Nft>,
+    #[account(mut, address = collection_key)]
+    pub collection: Account<'info, Collection>,
     #[account(mut)]
     pub owner: Signer<'info>,
     pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
 }
 
 #[derive(Accounts)]
@@ -313,6 +1171,53 @@ pub struct UpdateNft<'info> {
         seeds = [b"nft".as_ref(), _mint.as_ref()],
         bump)]
     pub nft: Account<'info, Nft>,
+    #[account(address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: bookings pda, may not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), _mint.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct DeleteNft<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump,
+        has_one = owner,
+        close = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: bookings pda, must not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), _mint.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    /// CHECK: auction pda, must not be initialized
+    #[account(
+        seeds = [b"auction".as_ref(), _mint.as_ref()],
+        bump)]
+    pub auction: UncheckedAccount<'info>,
+    /// CHECK: cnft pda, must not be initialized
+    #[account(
+        seeds = [b"cnft".as_ref(), _mint.as_ref()],
+        bump)]
+    pub cnft: UncheckedAccount<'info>,
+    /// CHECK: record of the latest rental, may be closed
+    #[account(mut,
+        seeds = [b"rental_record".as_ref(), _mint.as_ref(), nft.rental_count.saturating_sub(1).to_le_bytes().as_ref()],
+        bump)]
+    pub record: UncheckedAccount<'info>,
+    /// CHECK: metadata of the nft, checked when the collection has rented metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: checked when the collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
     #[account(mut)]
     pub owner: Signer<'info>,
 }
@@ -331,12 +1236,75 @@ pub struct RentNft<'info> {
         seeds = [b"nft".as_ref(), _mint.as_ref()],
         bump)]
     pub nft: Account<'info, Nft>,
+    /// CHECK: bookings pda, may not be initialized
+    #[account(mut,
+        seeds = [b"bookings".as_ref(), _mint.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    /// CHECK: nft access entry or renter token account, checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: collection access entry or renter token account, checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    #[account(
+        init, payer = renter,
+        space = RentalRecord::SIZE,
+        seeds = [b"rental_record".as_ref(), _mint.as_ref(), nft.rental_count.to_le_bytes().as_ref()],
+        bump)]
+    pub record: Account<'info, RentalRecord>,
+    /// CHECK: metadata of the nft, checked when the collection has rented metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: checked when the collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
     /// CHECK: unsafe
     #[account(mut)]
     pub owner: AccountInfo<'info>,
     #[account(mut)]
     pub renter: Signer<'info>,
     pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(_mint: Pubkey)]
+pub struct ReleaseRental<'info> {
+    #[account(mut,
+        seeds = [b"nft".as_ref(), _mint.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: record of the latest rental, may be closed
+    #[account(mut,
+        seeds = [b"rental_record".as_ref(), _mint.as_ref(), nft.rental_count.saturating_sub(1).to_le_bytes().as_ref()],
+        bump)]
+    pub record: UncheckedAccount<'info>,
+    /// CHECK: metadata of the nft, checked when the collection has rented metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: checked when the collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RentBundle<'info> {
+    /// CHECK: checked against the owner of every nft
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    /// CHECK: checked when a collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
 }
 
 #[account]
@@ -349,6 +1317,22 @@ pub struct Collection {
     pub website_url: String,
     // Application count
     pub app_count: u16,
+    // Nft listings referencing the collection
+    pub listing_count: u32,
+    // Rental stats, the volume sums payments across payment mints
+    pub total_rentals: u64,
+    pub volume: u64,
+    pub active_rentals: u32,
+    // Rental guardrails for holders
+    pub policy: RentalPolicy,
+    // Renters allowed by the collection
+    pub access: AccessPolicy,
+    // Metaplex collection nft of the mints minted under the collection,
+    // Pubkey::default() until minted
+    pub collection_mint: Pubkey,
+    pub authority: Pubkey,
+    // Metadata uri of rented nfts swapped to their rented variant
+    pub rented_metadata: bool,
 }
 
 impl Collection {
@@ -361,7 +1345,91 @@ impl Collection {
     + 4 + 4 * Collection::SYMBOL_MAX_LEN // symbol
     + 4 + 4 * Collection::URL_MAX_LEN // image_url
     + 4 + 4 * Collection::URL_MAX_LEN
-    + 2; // app_count
+    + 2 // app_count
+    + 4 // listing_count
+    + 8 // total_rentals
+    + 8 // volume
+    + 4 // active_rentals
+    + RentalPolicy::SIZE // policy
+    + AccessPolicy::SIZE // access
+    + 32 // collection_mint
+    + 32 // authority
+    + 1; // rented_metadata
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RentalPolicy {
+    pub rental_enabled: bool,
+    // Price per day bounds
+    pub min_price: u64,
+    pub max_price: u64,
+    pub max_days: u32,
+    // Accepted payment mints, any mint if empty
+    pub payment_mints: Vec<Pubkey>,
+    // Listing terms of nfts minted under the collection, unlisted if no days
+    pub default_price: u64,
+    pub default_max_days: u32,
+}
+
+impl Default for RentalPolicy {
+    fn default() -> Self {
+        RentalPolicy {
+            rental_enabled: true,
+            min_price: 0,
+            max_price: u64::MAX,
+            max_days: u32::MAX,
+            payment_mints: vec![],
+            default_price: 0,
+            default_max_days: 0,
+        }
+    }
+}
+
+impl RentalPolicy {
+    const PAYMENT_MINTS_MAX_LEN: usize = 5;
+
+    const SIZE: usize = 1 // rental_enabled
+    + 8 // min_price
+    + 8 // max_price
+    + 4 // max_days
+    + 4 + 32 * RentalPolicy::PAYMENT_MINTS_MAX_LEN // payment_mints
+    + 8 // default_price
+    + 4; // default_max_days
+
+    pub fn check_listing(
+        &self,
+        rental_max_days: u32,
+        rental_price: u64,
+        payment_mint: &Pubkey,
+    ) -> Result<()> {
+        if rental_price < self.min_price || rental_price > self.max_price {
+            return err!(ErrorCode::RentalPriceOutOfBounds);
+        }
+        if rental_max_days > self.max_days {
+            return err!(ErrorCode::InvalidRentalDuration);
+        }
+        if !self.payment_mints.is_empty() && !self.payment_mints.contains(payment_mint) {
+            return err!(ErrorCode::InvalidPaymentMint);
+        }
+        Ok(())
+    }
+
+    // Listing terms of nfts minted under the collection:
+    // (rental_max_days, rental_price, payment_mint)
+    pub fn default_listing(&self) -> (u32, u64, Pubkey) {
+        let payment_mint = self.payment_mints.first().copied().unwrap_or_default();
+        (self.default_max_days, self.default_price, payment_mint)
+    }
+
+    pub fn check_rental(&self, nft: &Nft, days: u32) -> Result<()> {
+        if !self.rental_enabled {
+            return err!(ErrorCode::CollectionRentalDisabled);
+        }
+        if days > self.max_days {
+            return err!(ErrorCode::InvalidRentalDuration);
+        }
+        self.check_listing(0, nft.rental_price, &nft.payment_mint)
+    }
 }
 
 #[account]
@@ -390,8 +1458,17 @@ pub struct Nft {
     pub rental_enabled: bool,
     pub rental_price: u64,
     pub rental_max_days: u32,
-    // Rental state
+    // Payment mint, Pubkey::default() for SOL
+    pub payment_mint: Pubkey,
+    // Renters allowed by the owner
+    pub access: AccessPolicy,
+    // Owner token account delegated to the program, Pubkey::default() if not delegated
+    pub token_account: Pubkey,
+    // Rental stats
     pub rental_count: u64,
+    pub total_earned: u64,
+    pub total_days_rented: u64,
+    // Rental state, the renter is reset once the rental is released
     pub renter: Pubkey,
     pub rented_until: i64,
 }
@@ -404,29 +1481,324 @@ impl Nft {
     + 1 // rental_enabled
     + 8 // rental_price
     + 4 // rental_max_days
+    + 32 // payment_mint
+    + AccessPolicy::SIZE // access
+    + 32 // token_account
     + 8 // rental_count
+    + 8 // total_earned
+    + 8 // total_days_rented
     + 32 // renter
     + 8; // rented_until
+
+    // Lists `mint` for rental, stats start over
+    #[allow(clippy::too_many_arguments)]
+    pub fn list(
+        &mut self,
+        mint: Pubkey,
+        collection: Pubkey,
+        owner: Pubkey,
+        rental_max_days: u32,
+        rental_price: u64,
+        rental_enabled: bool,
+        payment_mint: Pubkey,
+    ) {
+        self.mint = mint;
+        self.collection = collection;
+        self.owner = owner;
+        self.rental_max_days = rental_max_days;
+        self.rental_price = rental_price;
+        self.rental_count = 0;
+        self.total_earned = 0;
+        self.total_days_rented = 0;
+        self.rental_enabled = rental_enabled;
+        self.payment_mint = payment_mint;
+        self.access = AccessPolicy::Open;
+        self.token_account = Pubkey::default();
+        self.rented_until = 0;
+    }
+
+    pub fn is_rented(&self, timestamp: i64) -> bool {
+        self.rented_until > timestamp
+    }
+
+    pub fn check_rentable(&self, policy: &RentalPolicy, days: u32) -> Result<()> {
+        if !self.rental_enabled {
+            return err!(ErrorCode::NftNotListed);
+        }
+        if days > self.rental_max_days {
+            return err!(ErrorCode::InvalidRentalDuration);
+        }
+        // The collection policy may have changed since listing
+        policy.check_rental(self, days)
+    }
+
+    // Rents the nft to `renter`, updating the nft & collection stats
+    pub fn start_rental(
+        &mut self,
+        collection: &mut Collection,
+        renter: Pubkey,
+        rented_until: i64,
+        days: u32,
+        amount: u64,
+    ) -> Result<()> {
+        // The previous rental is over but was never released
+        if self.renter == Pubkey::default() {
+            collection.active_rentals = collection
+                .active_rentals
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        self.renter = renter;
+        self.rented_until = rented_until;
+        self.rental_count = checked_inc(self.rental_count, 1)?;
+        self.total_earned = checked_inc(self.total_earned, amount)?;
+        self.total_days_rented = checked_inc(self.total_days_rented, days as u64)?;
+        collection.total_rentals = checked_inc(collection.total_rentals, 1)?;
+        collection.volume = checked_inc(collection.volume, amount)?;
+        Ok(())
+    }
 }
 
+// Codes are grouped by section, every section starting at a multiple of 100
+// on top of the anchor error offset
 #[error_code]
 pub enum ErrorCode {
     // Checks
     #[msg("String too long")]
-    StringTooLong,
+    StringTooLong = 0,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Account mismatch")]
+    AccountMismatch,
     // Rental
     #[msg("Invalid owner")]
-    InvalidOwner,
+    InvalidOwner = 100,
     #[msg("Nft already rented")]
     NftRented,
     #[msg("Nft not listed")]
     NftNotListed,
     #[msg("Invalid rental duration")]
     InvalidRentalDuration,
-    #[msg("Insuffisant funds")]
-    InsuffisantFunds,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Nft not rented")]
+    NftNotRented,
+    #[msg("Not the current renter")]
+    NotRenter,
+    #[msg("Rental expired")]
+    RentalExpired,
+    // Collection
+    #[msg("Collection still has apps")]
+    CollectionHasApps = 200,
+    #[msg("Collection still has nft listings")]
+    CollectionHasListings,
+    #[msg("Invalid collection")]
+    InvalidCollection,
+    #[msg("Collection nft already minted")]
+    CollectionMintExists,
+    #[msg("Collection nft not minted")]
+    CollectionMintMissing,
+    // Policy
+    #[msg("Too many payment mints")]
+    TooManyPaymentMints = 300,
+    #[msg("Invalid rental policy")]
+    InvalidRentalPolicy,
+    #[msg("Rental price out of collection bounds")]
+    RentalPriceOutOfBounds,
+    #[msg("Payment mint not allowed")]
+    InvalidPaymentMint,
+    #[msg("Rental disabled for collection")]
+    CollectionRentalDisabled,
+    // Payment
+    #[msg("Missing payment accounts")]
+    MissingPaymentAccounts = 400,
+    #[msg("Invalid payment account")]
+    InvalidPaymentAccount,
+    #[msg("Invalid token program")]
+    InvalidTokenProgram,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Token-2022 nfts not supported")]
+    UnsupportedTokenProgram,
+    // Bookings
+    #[msg("Nft booked")]
+    NftBooked = 500,
+    #[msg("Invalid booking window")]
+    InvalidBookingWindow,
+    #[msg("Too many reservations")]
+    TooManyReservations,
+    #[msg("Reservation not found")]
+    ReservationNotFound,
+    #[msg("Invalid cancellation rules")]
+    InvalidCancellationRules,
+    // Auctions
+    #[msg("Invalid bidding period")]
+    InvalidBiddingPeriod = 600,
+    #[msg("Auction ended")]
+    AuctionEnded,
+    #[msg("Auction not ended")]
+    AuctionNotEnded,
+    #[msg("Bid too low")]
+    BidTooLow,
+    #[msg("Too many bids")]
+    TooManyBids,
+    #[msg("Missing bidder account")]
+    MissingBidderAccount,
+    #[msg("Auction has no bids")]
+    NoBids,
+    #[msg("Nft has an open auction")]
+    AuctionOpen,
+    // Offers
+    #[msg("Offer expired")]
+    OfferExpired = 700,
+    // Bundles
+    #[msg("Invalid bundle")]
+    InvalidBundle = 800,
+    // Access
+    #[msg("Renter access denied")]
+    AccessDenied = 900,
+    #[msg("Invalid access account")]
+    InvalidAccessAccount,
+    // Rental passes
+    #[msg("Rental pass not expired")]
+    PassNotExpired = 1000,
+    #[msg("Invalid rental pass accounts")]
+    InvalidPassAccounts,
+    // Delegation
+    #[msg("Invalid nft delegation")]
+    InvalidDelegation = 1100,
+    #[msg("Nft still delegated")]
+    NftDelegated,
+    // Rental records
+    #[msg("Invalid rental record account")]
+    InvalidRecordAccount = 1200,
+    #[msg("Rental record still retained")]
+    RecordRetained,
+    // Metadata
+    #[msg("Account not initialized")]
+    NotInitialized = 1300,
+    #[msg("Creator not verified")]
+    NotVerified,
+    #[msg("Invalid mint")]
+    InvalidMint,
+    #[msg("Invalid token amount")]
+    InvalidTokenAmount,
+    #[msg("Invalid metadata")]
+    InvalidMetadata,
+    #[msg("Invalid master edition")]
+    InvalidMasterEdition,
+    #[msg("Invalid creator")]
+    InvalidCreator,
+    #[msg("Creator shares must sum to 100")]
+    InvalidCreatorShares,
+    #[msg("Invalid seller fee")]
+    InvalidSellerFee,
+    // Config
+    #[msg("Program paused")]
+    ProgramPaused = 1400,
+    #[msg("Not the program upgrade authority")]
+    NotUpgradeAuthority,
+    // Mint gate
+    #[msg("Mint sold out")]
+    MintSoldOut = 1500,
+    // Compressed nfts
+    #[msg("Invalid asset id")]
+    InvalidAssetId = 1600,
+    #[msg("Invalid merkle tree")]
+    InvalidTree,
+    #[msg("Compressed nft listing, delisted by delete_cnft")]
+    CompressedListing,
+}
+
+// Lists every ErrorCode variant once, in declaration order, for clients to
+// map the code of a failed transaction back to its variant. A variant missing
+// from the list fails the exhaustive match
+macro_rules! error_codes {
+    ($($variant:ident),* $(,)?) => {
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant),*];
+
+            pub fn from_code(code: u32) -> Option<ErrorCode> {
+                Self::ALL.iter().copied().find(|error| u32::from(*error) == code)
+            }
+        }
+
+        #[allow(dead_code)]
+        fn error_codes_exhaustive(error: ErrorCode) {
+            match error {
+                $(ErrorCode::$variant)|* => {}
+            }
+        }
+    };
 }
 
+error_codes!(
+    StringTooLong,
+    MathOverflow,
+    AccountMismatch,
+    InvalidOwner,
+    NftRented,
+    NftNotListed,
+    InvalidRentalDuration,
+    InsufficientFunds,
+    NftNotRented,
+    NotRenter,
+    RentalExpired,
+    CollectionHasApps,
+    CollectionHasListings,
+    InvalidCollection,
+    CollectionMintExists,
+    CollectionMintMissing,
+    TooManyPaymentMints,
+    InvalidRentalPolicy,
+    RentalPriceOutOfBounds,
+    InvalidPaymentMint,
+    CollectionRentalDisabled,
+    MissingPaymentAccounts,
+    InvalidPaymentAccount,
+    InvalidTokenProgram,
+    InvalidTokenAccount,
+    UnsupportedTokenProgram,
+    NftBooked,
+    InvalidBookingWindow,
+    TooManyReservations,
+    ReservationNotFound,
+    InvalidCancellationRules,
+    InvalidBiddingPeriod,
+    AuctionEnded,
+    AuctionNotEnded,
+    BidTooLow,
+    TooManyBids,
+    MissingBidderAccount,
+    NoBids,
+    AuctionOpen,
+    OfferExpired,
+    InvalidBundle,
+    AccessDenied,
+    InvalidAccessAccount,
+    PassNotExpired,
+    InvalidPassAccounts,
+    InvalidDelegation,
+    NftDelegated,
+    InvalidRecordAccount,
+    RecordRetained,
+    NotInitialized,
+    NotVerified,
+    InvalidMint,
+    InvalidTokenAmount,
+    InvalidMetadata,
+    InvalidMasterEdition,
+    InvalidCreator,
+    InvalidCreatorShares,
+    InvalidSellerFee,
+    ProgramPaused,
+    NotUpgradeAuthority,
+    MintSoldOut,
+    InvalidAssetId,
+    InvalidTree,
+    CompressedListing,
+);
+
 // Utils
 pub fn transfer_funds<'a>(
     from: &AccountInfo<'a>,
@@ -442,3 +1814,89 @@ pub fn transfer_funds<'a>(
     )?;
     Ok(())
 }
+
+// Pays `to` in SOL or, for an spl payment mint, through the [from token
+// account, to token account, token program, payment mint] remaining accounts.
+// Token-2022 transfer fees are paid on top, `to` receiving `amount`
+pub fn transfer_payment<'a>(
+    payment_mint: &Pubkey,
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    remaining_accounts: &[AccountInfo<'a>],
+    amount: u64,
+) -> Result<()> {
+    if *payment_mint == Pubkey::default() {
+        return transfer_funds(from, to, system_program, amount);
+    }
+    if remaining_accounts.len() < 4 {
+        return err!(ErrorCode::MissingPaymentAccounts);
+    }
+    let from_token = token_interface::token_account(&remaining_accounts[0])?;
+    let to_token = token_interface::token_account(&remaining_accounts[1])?;
+    let token_program = &remaining_accounts[2];
+    let mint_info = &remaining_accounts[3];
+    if mint_info.key() != *payment_mint
+        || from_token.mint != *payment_mint
+        || to_token.mint != *payment_mint
+    {
+        return err!(ErrorCode::InvalidPaymentMint);
+    }
+    if from_token.owner != from.key() || to_token.owner != to.key() {
+        return err!(ErrorCode::InvalidPaymentAccount);
+    }
+    // The mint & both accounts belong to the program doing the transfer
+    if !token_interface::is_token_program(token_program.key)
+        || mint_info.owner != token_program.key
+        || remaining_accounts[0].owner != token_program.key
+        || remaining_accounts[1].owner != token_program.key
+    {
+        return err!(ErrorCode::InvalidTokenProgram);
+    }
+    let mint = token_interface::mint(mint_info)?;
+    let fee = token_interface::transfer_fee(mint_info, amount)?;
+    token_interface::transfer_checked(
+        token_program,
+        &remaining_accounts[0],
+        mint_info,
+        &remaining_accounts[1],
+        from,
+        checked_inc(amount, fee)?,
+        mint.decimals,
+        &[],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_are_sectioned() {
+        assert_eq!(u32::from(ErrorCode::StringTooLong), 6000);
+        assert_eq!(u32::from(ErrorCode::InvalidOwner), 6100);
+        assert_eq!(u32::from(ErrorCode::NftBooked), 6500);
+        assert_eq!(u32::from(ErrorCode::InvalidCreator), 7306);
+    }
+
+    #[test]
+    fn error_codes_round_trip() {
+        for error in ErrorCode::ALL {
+            let code = u32::from(*error);
+            assert_eq!(ErrorCode::from_code(code).map(u32::from), Some(code));
+        }
+        assert!(ErrorCode::from_code(0).is_none());
+        assert_eq!(
+            ErrorCode::from_code(6101).map(|error| format!("{:?}", error)),
+            Some("NftRented".to_string())
+        );
+    }
+
+    #[test]
+    fn error_codes_are_listed_in_order() {
+        // Increasing codes, no variant listed twice
+        assert!(ErrorCode::ALL
+            .windows(2)
+            .all(|pair| u32::from(pair[0]) < u32::from(pair[1])));
+    }
+}
@@ -0,0 +1,81 @@
+use anchor_lang::prelude::*;
+
+use crate::{ErrorCode, DAY_S};
+
+// Price of a `days` long rental at `price` per day
+pub fn rental_amount(price: u64, days: u32) -> Result<u64> {
+    price
+        .checked_mul(days as u64)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+// End of a `days` long rental starting at `start`
+pub fn rental_end(start: i64, days: u32) -> Result<i64> {
+    DAY_S
+        .checked_mul(days as i64)
+        .and_then(|duration| start.checked_add(duration))
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+// Adds `amount` to a u64 counter or total
+pub fn checked_inc(value: u64, amount: u64) -> Result<u64> {
+    value
+        .checked_add(amount)
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+// `percent` of `amount`, rounded down
+pub fn percent_of(amount: u64, percent: u8) -> Result<u64> {
+    let part = amount as u128 * percent as u128 / 100;
+    u64::try_from(part).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn rental_amount_never_wraps(price in any::<u64>(), days in any::<u32>()) {
+            match rental_amount(price, days) {
+                Ok(amount) => prop_assert_eq!(amount as u128, price as u128 * days as u128),
+                Err(_) => prop_assert!(price as u128 * days as u128 > u64::MAX as u128),
+            }
+        }
+
+        #[test]
+        fn rental_end_never_wraps(start in any::<i64>(), days in any::<u32>()) {
+            let expected = start as i128 + DAY_S as i128 * days as i128;
+            match rental_end(start, days) {
+                Ok(end) => prop_assert_eq!(end as i128, expected),
+                Err(_) => prop_assert!(expected > i64::MAX as i128),
+            }
+        }
+
+        #[test]
+        fn rental_end_extreme_durations(start in 0..i64::MAX / 2) {
+            // u32::MAX days always fit from a realistic timestamp
+            prop_assert!(rental_end(start, u32::MAX).unwrap() > start);
+        }
+
+        #[test]
+        fn percent_of_is_bounded(amount in any::<u64>(), percent in 0..=100u8) {
+            prop_assert!(percent_of(amount, percent).unwrap() <= amount);
+        }
+    }
+
+    #[test]
+    fn rental_amount_extreme_prices() {
+        assert_eq!(rental_amount(u64::MAX, 1).unwrap(), u64::MAX);
+        assert!(rental_amount(u64::MAX, 2).is_err());
+        assert!(rental_amount(u64::MAX, u32::MAX).is_err());
+        assert_eq!(rental_amount(0, u32::MAX).unwrap(), 0);
+    }
+
+    #[test]
+    fn rental_end_extreme_start() {
+        assert!(rental_end(i64::MAX, 1).is_err());
+        assert_eq!(rental_end(i64::MAX, 0).unwrap(), i64::MAX);
+    }
+}
@@ -1,34 +1,92 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount};
-use mpl_token_metadata::instruction::{create_master_edition_v3, create_metadata_accounts_v2};
-use mpl_token_metadata::state::{Metadata, EDITION, PREFIX};
-
-#[macro_export]
-macro_rules! assert_eq {
-    ($left:expr, $right:expr, $($arg:tt)+) => ({
-        match (&$left, &$right) {
-            (left_val, right_val) => {
-                if !(*left_val == *right_val) {
-                    msg!($($arg)+);
-                }
-            }
-        }
-    });
+use mpl_token_metadata::instruction::{
+    create_master_edition_v3, create_metadata_accounts_v2, freeze_delegated_account,
+    mint_new_edition_from_master_edition_via_token, sign_metadata, thaw_delegated_account,
+    update_metadata_accounts_v2, verify_collection,
+};
+use mpl_token_metadata::state::{
+    Collection as MetadataCollection, Creator, DataV2, Metadata, EDITION, EDITION_MARKER_BIT_SIZE,
+    PREFIX,
+};
+
+use crate::config::Config;
+use crate::token_interface;
+use crate::{Collection, ErrorCode};
+
+// Local deployment of the token metadata program
+pub static TOKEN_METADATA_PROGRAM_ID: &str = "RTSE3BtLs2dDR482uuKMvdcGwWnbbQqUFEkxnTZC7FG";
+
+// Most creators the token metadata program accepts
+pub const MAX_CREATORS: usize = 5;
+pub const MAX_SELLER_FEE_BASIS_POINTS: u16 = 10000;
+
+// Creator of a minted nft, verified by the program when it is the creator
+// authority pda
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct CreatorShare {
+    pub address: Pubkey,
+    pub share: u8,
+}
+
+// Checks the creators shares and the seller fee before minting
+pub fn check_creators(creators: &[CreatorShare], seller_fee_basis_points: u16) -> Result<()> {
+    require!(
+        !creators.is_empty() && creators.len() <= MAX_CREATORS,
+        ErrorCode::InvalidCreatorShares
+    );
+    let total: u16 = creators.iter().map(|creator| creator.share as u16).sum();
+    require!(total == 100, ErrorCode::InvalidCreatorShares);
+    require!(
+        seller_fee_basis_points <= MAX_SELLER_FEE_BASIS_POINTS,
+        ErrorCode::InvalidSellerFee
+    );
+    Ok(())
+}
+
+// The program only verifies itself as creator of mints of one of its
+// collections, signed by the collection authority.
+// Accounts: [collection, collection authority]
+fn check_creator_authority(
+    accounts: &[AccountInfo],
+    collection_mint: Option<Pubkey>,
+) -> Result<()> {
+    let (collection, authority) = match accounts {
+        [collection, authority, ..] => (Account::<Collection>::try_from(collection)?, authority),
+        _ => return err!(ErrorCode::InvalidCreator),
+    };
+    require!(
+        authority.is_signer
+            && collection.authority == authority.key()
+            && collection.collection_mint != Pubkey::default()
+            && collection_mint == Some(collection.collection_mint),
+        ErrorCode::InvalidCreator
+    );
+    Ok(())
 }
 
-pub fn mint_nft(
-    ctx: Context<MintNFT>,
-    creator_key: Pubkey,
+// Remaining accounts: the check_creator_authority accounts, when the creator
+// authority pda is one of the creators
+#[allow(clippy::too_many_arguments)]
+pub fn mint_nft<'info>(
+    ctx: Context<'_, '_, '_, 'info, MintNFT<'info>>,
     uri: String,
     title: String,
     symbol: String,
+    creators: Vec<CreatorShare>,
+    seller_fee_basis_points: u16,
+    collection_mint: Option<Pubkey>,
+    max_supply: Option<u64>,
 ) -> Result<()> {
+    check_creators(&creators, seller_fee_basis_points)?;
+
     msg!("Initializing Mint NFT");
     let cpi_accounts = MintTo {
         mint: ctx.accounts.mint.to_account_info(),
         to: ctx.accounts.token_account.to_account_info(),
-        authority: ctx.accounts.payer.to_account_info(),
+        authority: ctx.accounts.mint_authority.to_account_info(),
     };
     msg!("CPI Accounts Assigned");
     let cpi_program = ctx.accounts.token_program.to_account_info();
@@ -37,102 +95,419 @@ pub fn mint_nft(
     msg!("CPI Context Assigned");
     token::mint_to(cpi_ctx, 1)?;
     msg!("Token Minted !!!");
-    // if uri.len() > 1 {
-    //     return Ok(());
-    // }
-    let account_info = vec![
-        ctx.accounts.metadata.to_account_info(),
-        ctx.accounts.mint.to_account_info(),
-        ctx.accounts.mint_authority.to_account_info(),
-        ctx.accounts.payer.to_account_info(),
-        ctx.accounts.token_metadata_program.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        ctx.accounts.rent.to_account_info(),
-    ];
-    msg!("Account Info Assigned");
-    let creator = vec![
-        mpl_token_metadata::state::Creator {
-            address: creator_key,
-            verified: false,
-            share: 100,
-        },
-        mpl_token_metadata::state::Creator {
-            address: ctx.accounts.mint_authority.key(),
+    let creator_authority = ctx.accounts.creator_authority.key();
+    let signs_creator = creators
+        .iter()
+        .any(|creator| creator.address == creator_authority);
+    if signs_creator {
+        check_creator_authority(ctx.remaining_accounts, collection_mint)?;
+    }
+    let creators = creators
+        .into_iter()
+        .map(|creator| Creator {
+            address: creator.address,
             verified: false,
-            share: 0,
-        },
-    ];
+            share: creator.share,
+        })
+        .collect();
     msg!("Creator Assigned");
-    invoke(
-        &create_metadata_accounts_v2(
+    create_metadata(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.metadata,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.mint_authority,
+        &ctx.accounts.payer,
+        // Update
+        &ctx.accounts.payer,
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.rent.to_account_info(),
+        title,
+        symbol,
+        uri,
+        Some(creators),
+        seller_fee_basis_points,
+        collection_mint,
+        true,
+        &[],
+    )?;
+    msg!("Metadata Account Created !!!");
+    if signs_creator {
+        let bump = *ctx.bumps.get("creator_authority").unwrap();
+        sign_creator(
+            &ctx.accounts.token_metadata_program,
+            &ctx.accounts.metadata,
+            &ctx.accounts.creator_authority,
+            &[&[b"creator".as_ref(), &[bump]]],
+        )?;
+        msg!("Creator Verified !!!");
+    }
+    create_master_edition(
+        &ctx.accounts.token_metadata_program,
+        &ctx.accounts.master_edition,
+        &ctx.accounts.mint.to_account_info(),
+        &ctx.accounts.mint_authority,
+        &ctx.accounts.payer,
+        &ctx.accounts.metadata,
+        // Update
+        &ctx.accounts.payer,
+        &ctx.accounts.token_program.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        &ctx.accounts.rent.to_account_info(),
+        max_supply,
+        &[],
+    )?;
+    msg!("Master Edition Nft Minted !!!");
+    Ok(())
+}
+
+// Prints edition number `edition` of a master edition with a supply, to the
+// master edition holder. Prints can be listed with create_nft like any mint
+pub fn print_edition(ctx: Context<PrintEdition>, edition: u64) -> Result<()> {
+    let owner = &ctx.accounts.owner;
+
+    token_interface::require_token_program(&ctx.accounts.master_mint)?;
+    token_interface::require_token_program(&ctx.accounts.master_token_account)?;
+    let master_token_account = token_interface::token_account(&ctx.accounts.master_token_account)?;
+    if master_token_account.owner != owner.key() {
+        return err!(ErrorCode::InvalidOwner);
+    }
+    if master_token_account.mint != ctx.accounts.master_mint.key() {
+        return err!(ErrorCode::InvalidMint);
+    }
+    if master_token_account.amount != 1 {
+        return err!(ErrorCode::InvalidTokenAmount);
+    }
+
+    let cpi_accounts = MintTo {
+        mint: ctx.accounts.new_mint.to_account_info(),
+        to: ctx.accounts.new_token_account.to_account_info(),
+        authority: owner.to_account_info(),
+    };
+    token::mint_to(
+        CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts),
+        1,
+    )?;
+    invoke_signed(
+        &mint_new_edition_from_master_edition_via_token(
             ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.metadata.key(),
-            ctx.accounts.mint.key(),
-            ctx.accounts.mint_authority.key(),
-            ctx.accounts.payer.key(),
-            // Update 
-            ctx.accounts.payer.key(),
-            // 
+            ctx.accounts.new_metadata.key(),
+            ctx.accounts.new_edition.key(),
+            ctx.accounts.master_edition.key(),
+            ctx.accounts.new_mint.key(),
+            owner.key(),
+            owner.key(),
+            owner.key(),
+            ctx.accounts.master_token_account.key(),
+            owner.key(),
+            ctx.accounts.master_metadata.key(),
+            ctx.accounts.master_mint.key(),
+            edition,
+        ),
+        &[
+            ctx.accounts.new_metadata.to_account_info(),
+            ctx.accounts.new_edition.to_account_info(),
+            ctx.accounts.master_edition.to_account_info(),
+            ctx.accounts.new_mint.to_account_info(),
+            ctx.accounts.edition_marker.to_account_info(),
+            owner.to_account_info(),
+            ctx.accounts.master_token_account.to_account_info(),
+            ctx.accounts.master_metadata.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+            ctx.accounts.token_metadata_program.to_account_info(),
+        ],
+        &[],
+    )?;
+    Ok(())
+}
+
+// Creates the metadata account of `mint`, signing with `signer_seeds` for
+// program owned authorities
+#[allow(clippy::too_many_arguments)]
+pub fn create_metadata<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    mint_authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    update_authority: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    title: String,
+    symbol: String,
+    uri: String,
+    creators: Option<Vec<Creator>>,
+    seller_fee_basis_points: u16,
+    collection_mint: Option<Pubkey>,
+    is_mutable: bool,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let account_info = vec![
+        metadata.to_account_info(),
+        mint.to_account_info(),
+        mint_authority.to_account_info(),
+        payer.to_account_info(),
+        update_authority.to_account_info(),
+        token_metadata_program.to_account_info(),
+        system_program.to_account_info(),
+        rent.to_account_info(),
+    ];
+    invoke_signed(
+        &create_metadata_accounts_v2(
+            token_metadata_program.key(),
+            metadata.key(),
+            mint.key(),
+            mint_authority.key(),
+            payer.key(),
+            update_authority.key(),
             title,
             symbol,
             uri,
-            Some(creator),
-            0,
-            true,
+            creators,
+            seller_fee_basis_points,
             true,
-            None,
+            is_mutable,
+            collection_mint.map(|key| MetadataCollection {
+                verified: false,
+                key,
+            }),
             None,
         ),
         account_info.as_slice(),
+        signer_seeds,
     )?;
-    msg!("Metadata Account Created !!!");
-    let master_edition_infos = vec![
-        ctx.accounts.master_edition.to_account_info(),
-        ctx.accounts.mint.to_account_info(),
-        ctx.accounts.mint_authority.to_account_info(),
-        ctx.accounts.payer.to_account_info(),
-        ctx.accounts.metadata.to_account_info(),
-        ctx.accounts.token_metadata_program.to_account_info(),
-        ctx.accounts.token_program.to_account_info(),
-        ctx.accounts.system_program.to_account_info(),
-        ctx.accounts.rent.to_account_info(),
-    ];
-    msg!("Master Edition Account Infos Assigned");
-    invoke(
+    Ok(())
+}
+
+// Updates the metadata data and mutability, signing with `signer_seeds` for
+// program owned update authorities
+pub fn update_metadata<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    update_authority: &AccountInfo<'info>,
+    data: Option<DataV2>,
+    is_mutable: Option<bool>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
+        &update_metadata_accounts_v2(
+            token_metadata_program.key(),
+            metadata.key(),
+            update_authority.key(),
+            None,
+            data,
+            None,
+            is_mutable,
+        ),
+        &[
+            metadata.to_account_info(),
+            update_authority.to_account_info(),
+            token_metadata_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Current data of `metadata`, to update through update_metadata
+pub fn metadata_data(metadata: &Metadata) -> DataV2 {
+    DataV2 {
+        name: metadata.data.name.trim_end_matches('\0').to_string(),
+        symbol: metadata.data.symbol.trim_end_matches('\0').to_string(),
+        uri: metadata.data.uri.trim_end_matches('\0').to_string(),
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        creators: metadata.data.creators.clone(),
+        collection: metadata.collection.clone(),
+        uses: metadata.uses.clone(),
+    }
+}
+
+// Creates the master edition of `mint`, taking over its mint authority,
+// signing with `signer_seeds` for program owned authorities
+#[allow(clippy::too_many_arguments)]
+pub fn create_master_edition<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    master_edition: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    mint_authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    update_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    max_supply: Option<u64>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
         &create_master_edition_v3(
-            ctx.accounts.token_metadata_program.key(),
-            ctx.accounts.master_edition.key(),
-            ctx.accounts.mint.key(),
-            ctx.accounts.payer.key(),
-            ctx.accounts.mint_authority.key(),
-            ctx.accounts.metadata.key(),
-            ctx.accounts.payer.key(),
-            Some(0),
+            token_metadata_program.key(),
+            master_edition.key(),
+            mint.key(),
+            update_authority.key(),
+            mint_authority.key(),
+            metadata.key(),
+            payer.key(),
+            max_supply,
         ),
-        master_edition_infos.as_slice(),
+        &[
+            master_edition.to_account_info(),
+            mint.to_account_info(),
+            mint_authority.to_account_info(),
+            payer.to_account_info(),
+            metadata.to_account_info(),
+            update_authority.to_account_info(),
+            token_metadata_program.to_account_info(),
+            token_program.to_account_info(),
+            system_program.to_account_info(),
+            rent.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Verifies the collection set on `metadata`, `collection_authority` being
+// the update authority of the collection nft
+#[allow(clippy::too_many_arguments)]
+pub fn verify_collection_item<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    collection_authority: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    collection_mint: &AccountInfo<'info>,
+    collection_metadata: &AccountInfo<'info>,
+    collection_master_edition: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
+        &verify_collection(
+            token_metadata_program.key(),
+            metadata.key(),
+            collection_authority.key(),
+            payer.key(),
+            collection_mint.key(),
+            collection_metadata.key(),
+            collection_master_edition.key(),
+            None,
+        ),
+        &[
+            metadata.to_account_info(),
+            collection_authority.to_account_info(),
+            payer.to_account_info(),
+            collection_mint.to_account_info(),
+            collection_metadata.to_account_info(),
+            collection_master_edition.to_account_info(),
+            token_metadata_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Verifies `creator` on the metadata, signing with `signer_seeds` for the
+// program creator authority
+pub fn sign_creator<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    creator: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
+        &sign_metadata(token_metadata_program.key(), metadata.key(), creator.key()),
+        &[
+            metadata.to_account_info(),
+            creator.to_account_info(),
+            token_metadata_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Freezes a token account the program is delegate of
+pub fn freeze_delegated<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    delegate: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    edition: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
+        &freeze_delegated_account(
+            token_metadata_program.key(),
+            delegate.key(),
+            token_account.key(),
+            edition.key(),
+            mint.key(),
+        ),
+        &[
+            delegate.to_account_info(),
+            token_account.to_account_info(),
+            edition.to_account_info(),
+            mint.to_account_info(),
+            token_program.to_account_info(),
+            token_metadata_program.to_account_info(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+// Thaws a token account frozen with `freeze_delegated`
+pub fn thaw_delegated<'info>(
+    token_metadata_program: &AccountInfo<'info>,
+    delegate: &AccountInfo<'info>,
+    token_account: &AccountInfo<'info>,
+    edition: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    invoke_signed(
+        &thaw_delegated_account(
+            token_metadata_program.key(),
+            delegate.key(),
+            token_account.key(),
+            edition.key(),
+            mint.key(),
+        ),
+        &[
+            delegate.to_account_info(),
+            token_account.to_account_info(),
+            edition.to_account_info(),
+            mint.to_account_info(),
+            token_program.to_account_info(),
+            token_metadata_program.to_account_info(),
+        ],
+        signer_seeds,
     )?;
-    msg!("Master Edition Nft Minted !!!");
     Ok(())
 }
 
 pub fn verify_nft(ctx: Context<VerifyNFT>) -> Result<()> {
-    let nft_token_account = &ctx.accounts.nft_token_account;
+    let nft_token_account = token_interface::token_account(&ctx.accounts.nft_token_account)?;
     let user = &ctx.accounts.user;
     let nft_mint_account = &ctx.accounts.nft_mint;
+    token_interface::mint(nft_mint_account)?;
 
     // Check the owner of the token account
-    assert_eq!(nft_token_account.owner, user.key(), "Invalid owner");
+    require!(
+        nft_token_account.owner == user.key(),
+        ErrorCode::InvalidOwner
+    );
 
     // Check the mint on the token account
-    assert_eq!(
-        nft_token_account.mint,
-        nft_mint_account.key(),
-        "Invalid mint"
+    require!(
+        nft_token_account.mint == nft_mint_account.key(),
+        ErrorCode::InvalidMint
     );
 
     // Check the amount on the token account
-    assert_eq!(nft_token_account.amount, 1, "Invalid amount");
+    require!(nft_token_account.amount == 1, ErrorCode::InvalidTokenAmount);
 
     // Check master edition
     // --------------------
@@ -147,16 +522,13 @@ pub fn verify_nft(ctx: Context<VerifyNFT>) -> Result<()> {
     let (master_edition_key, _) =
         Pubkey::find_program_address(master_edition_seed, ctx.accounts.token_metadata_program.key);
 
-    assert_eq!(
-        master_edition_key,
-        ctx.accounts.master_edition.key(),
-        "Invalid master edition"
+    require!(
+        master_edition_key == ctx.accounts.master_edition.key(),
+        ErrorCode::InvalidMasterEdition
     );
 
     if ctx.accounts.master_edition.data_is_empty() {
-        // return err!(ErrorCode::NotInitialized);
-        // TODO! Error
-        msg!("Master edition not initialized error");
+        return err!(ErrorCode::NotInitialized);
     }
 
     // Verify metadata account
@@ -177,85 +549,187 @@ pub fn verify_nft(ctx: Context<VerifyNFT>) -> Result<()> {
         Pubkey::find_program_address(metadata_seed, ctx.accounts.token_metadata_program.key);
 
     // Check that the derived key is the current metadata account key
-    assert_eq!(
-        metadata_derived_key,
-        nft_metadata_account.key(),
-        "Invalid metadata"
+    require!(
+        metadata_derived_key == nft_metadata_account.key(),
+        ErrorCode::InvalidMetadata
     );
 
     // Check if init
     if nft_metadata_account.data_is_empty() {
-        // return err!(ErrorCode::NotInitialized);
-        // TODO! Error
-        msg!("Metadata not initialized error");
+        return err!(ErrorCode::NotInitialized);
     }
 
     // Extract metadata
     // ----------------
 
-    let metadata_full_account = &mut Metadata::from_account_info(&nft_metadata_account)?;
-    let full_metadata_clone = metadata_full_account.clone();
+    let metadata = Metadata::from_account_info(nft_metadata_account)
+        .map_err(|_| error!(ErrorCode::InvalidMetadata))?;
     // let expected_creator = Pubkey::("creator_str").unwrap();
     let expected_creator_b58 = "4cjdo4NKwgsTqCpoBob9gd9oaBTeXdRV6TP5B7ye7UzP";
     let expected_creator = expected_creator_b58.parse::<Pubkey>().unwrap();
 
-    msg!(
-        "Creator in metadata: {}",
-        full_metadata_clone.data.creators.as_ref().unwrap()[0].address
-    );
-    msg!("Expected: {}", expected_creator);
     // Make sure expected creator is present in metadata
-    assert_eq!(
-        full_metadata_clone.data.creators.as_ref().unwrap()[0].address,
-        expected_creator,
-        "Invalid creator"
+    let creator = metadata
+        .data
+        .creators
+        .as_ref()
+        .and_then(|creators| creators.first())
+        .ok_or(ErrorCode::InvalidCreator)?;
+    msg!("Creator in metadata: {}", creator.address);
+    msg!("Expected: {}", expected_creator);
+    require!(
+        creator.address == expected_creator,
+        ErrorCode::InvalidCreator
     );
-
-    if !full_metadata_clone.data.creators.unwrap()[0].verified {
-        // return err!(ErrorCode::NotVerified);
-        // TODO! errors
-        msg!("Not verified error");
-    }
+    require!(creator.verified, ErrorCode::NotVerified);
 
     Ok(())
 }
 
 #[derive(Accounts)]
 pub struct MintNFT<'info> {
-    #[account(mut)]
     pub mint_authority: Signer<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub mint: UncheckedAccount<'info>,
-    // #[account(mut)]
+    #[account(
+        init, payer = payer,
+        mint::decimals = 0,
+        mint::authority = mint_authority,
+        mint::freeze_authority = mint_authority)]
+    pub mint: Account<'info, Mint>,
     pub token_program: Program<'info, Token>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
+    /// CHECK: created by the token metadata program
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
     pub metadata: UncheckedAccount<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
-    pub token_account: UncheckedAccount<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
+    #[account(
+        init, payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = payer)]
+    pub token_account: Account<'info, TokenAccount>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
     pub token_metadata_program: UncheckedAccount<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
     #[account(mut)]
-    pub payer: AccountInfo<'info>,
+    pub payer: Signer<'info>,
     pub system_program: Program<'info, System>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    pub rent: AccountInfo<'info>,
-    /// CHECK: This is not dangerous because we don't read or write from this account
-    #[account(mut)]
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: created by the token metadata program
+    #[account(mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            token_metadata_program.key().as_ref(),
+            mint.key().as_ref(),
+            EDITION.as_bytes(),
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump)]
     pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: pda verifying itself as creator
+    #[account(
+        seeds = [b"creator".as_ref()],
+        bump)]
+    pub creator_authority: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(edition: u64)]
+pub struct PrintEdition<'info> {
+    #[account(
+        init, payer = owner,
+        mint::decimals = 0,
+        mint::authority = owner,
+        mint::freeze_authority = owner)]
+    pub new_mint: Account<'info, Mint>,
+    #[account(
+        init, payer = owner,
+        associated_token::mint = new_mint,
+        associated_token::authority = owner)]
+    pub new_token_account: Account<'info, TokenAccount>,
+    /// CHECK: created by the token metadata program
+    #[account(mut,
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), new_mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub new_metadata: UncheckedAccount<'info>,
+    /// CHECK: created by the token metadata program
+    #[account(mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            token_metadata_program.key().as_ref(),
+            new_mint.key().as_ref(),
+            EDITION.as_bytes(),
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub new_edition: UncheckedAccount<'info>,
+    // Master edition mint, of the Token program
+    /// CHECK: checked against the token program
+    pub master_mint: AccountInfo<'info>,
+    // Token account holding the master edition
+    /// CHECK: parsed by the token interface
+    pub master_token_account: AccountInfo<'info>,
+    /// CHECK: checked by the token metadata program
+    #[account(
+        seeds = [PREFIX.as_bytes(), token_metadata_program.key().as_ref(), master_mint.key().as_ref()],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub master_metadata: UncheckedAccount<'info>,
+    /// CHECK: checked by the token metadata program, counts the prints
+    #[account(mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            token_metadata_program.key().as_ref(),
+            master_mint.key().as_ref(),
+            EDITION.as_bytes(),
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub master_edition: UncheckedAccount<'info>,
+    /// CHECK: marks the edition number as printed, created by the token metadata program
+    #[account(mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            token_metadata_program.key().as_ref(),
+            master_mint.key().as_ref(),
+            EDITION.as_bytes(),
+            (edition / EDITION_MARKER_BIT_SIZE).to_string().as_bytes(),
+        ],
+        seeds::program = token_metadata_program.key(),
+        bump)]
+    pub edition_marker: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: checked against the program id
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
 }
 
 #[derive(Accounts)]
 pub struct VerifyNFT<'info> {
     // Nft owner
     pub user: Signer<'info>,
-    // Mint account
-    pub nft_mint: Account<'info, Mint>,
+    // Mint account, of the Token or Token-2022 program
+    /// CHECK: parsed by the token interface
+    pub nft_mint: AccountInfo<'info>,
     // Token account holding the NFT
-    pub nft_token_account: Account<'info, TokenAccount>,
+    /// CHECK: parsed by the token interface
+    pub nft_token_account: AccountInfo<'info>,
     // Metadata account of the NFT
     /// CHECK: unsure
     pub nft_metadata_account: AccountInfo<'info>,
@@ -264,16 +738,37 @@ pub struct VerifyNFT<'info> {
     pub master_edition: AccountInfo<'info>,
     // Token metadata program
     /// CHECK: unsure
-    #[account(address = "RTSE3BtLs2dDR482uuKMvdcGwWnbbQqUFEkxnTZC7FG"
-    .parse::<Pubkey>()
-    .unwrap())]
+    #[account(address = TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap())]
     pub token_metadata_program: AccountInfo<'info>,
 }
 
-#[error_code]
-pub enum ErrorCode {
-    #[msg("Not initialized")]
-    NotInitialized,
-    #[msg("Not verified")]
-    NotVerified,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shares(shares: &[u8]) -> Vec<CreatorShare> {
+        shares
+            .iter()
+            .map(|share| CreatorShare {
+                address: Pubkey::new_unique(),
+                share: *share,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn creator_shares_sum_to_100() {
+        assert!(check_creators(&shares(&[100]), 500).is_ok());
+        assert!(check_creators(&shares(&[60, 40, 0]), 0).is_ok());
+        assert!(check_creators(&shares(&[]), 0).is_err());
+        assert!(check_creators(&shares(&[50, 49]), 0).is_err());
+        assert!(check_creators(&shares(&[255, 255]), 0).is_err());
+        assert!(check_creators(&shares(&[20; 6]), 0).is_err());
+    }
+
+    #[test]
+    fn seller_fee_is_bounded() {
+        assert!(check_creators(&shares(&[100]), MAX_SELLER_FEE_BASIS_POINTS).is_ok());
+        assert!(check_creators(&shares(&[100]), MAX_SELLER_FEE_BASIS_POINTS + 1).is_err());
+    }
 }
@@ -0,0 +1,318 @@
+use anchor_lang::prelude::*;
+
+use crate::access::check_access;
+use crate::bookings::{check_not_booked, transfer_escrow};
+use crate::config::Config;
+use crate::delegation::freeze_rented;
+use crate::math::{rental_amount, rental_end};
+use crate::records::RentalRecord;
+use crate::rented_metadata::sync_rented_metadata;
+use crate::time::now;
+use crate::vault::Vault;
+use crate::{transfer_funds, Collection, ErrorCode, Nft};
+
+// Remaining accounts, for spl payment mints: [renter token account, offer
+// vault, token program, payment mint]
+pub fn create_rental_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreateRentalOffer<'info>>,
+    mint: Pubkey,
+    price: u64,
+    days: u32,
+    expires_at: i64,
+    nft_proof: Vec<[u8; 32]>,
+    collection_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let nft = &ctx.accounts.nft;
+    let offer = &mut ctx.accounts.offer;
+    let renter = &ctx.accounts.renter;
+
+    let timestamp = now()?;
+    if expires_at <= timestamp {
+        return err!(ErrorCode::OfferExpired);
+    }
+    if days == 0 {
+        return err!(ErrorCode::InvalidRentalDuration);
+    }
+    check_access(
+        &nft.access,
+        &nft.key(),
+        &renter.key(),
+        &ctx.accounts.nft_access,
+        &nft_proof,
+        ctx.program_id,
+    )?;
+    check_access(
+        &ctx.accounts.collection.access,
+        &nft.collection,
+        &renter.key(),
+        &ctx.accounts.collection_access,
+        &collection_proof,
+        ctx.program_id,
+    )?;
+
+    // Escrow the whole rental in the offer account, or its vault, in the nft
+    // payment mint
+    let amount = rental_amount(price, days)?;
+    if nft.payment_mint == Pubkey::default() {
+        transfer_funds(
+            &renter.to_account_info(),
+            &offer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+    } else {
+        let (from, vault_accounts) = ctx
+            .remaining_accounts
+            .split_first()
+            .ok_or(ErrorCode::MissingPaymentAccounts)?;
+        Vault::from_accounts(vault_accounts, &offer.key(), &nft.payment_mint)?.deposit(
+            from,
+            &renter.to_account_info(),
+            amount,
+        )?;
+    }
+
+    offer.mint = mint;
+    offer.payment_mint = nft.payment_mint;
+    offer.renter = renter.key();
+    offer.price = price;
+    offer.days = days;
+    offer.expires_at = expires_at;
+    Ok(())
+}
+
+// Remaining accounts: for spl payment mints [owner token account, offer vault,
+// token program, payment mint], then the delegation accounts of a delegated
+// nft
+pub fn accept_rental_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, AcceptRentalOffer<'info>>,
+    nft_proof: Vec<[u8; 32]>,
+    collection_proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let nft = &mut ctx.accounts.nft;
+    let offer = &ctx.accounts.offer;
+    let owner = &ctx.accounts.owner;
+    let policy = &ctx.accounts.collection.policy;
+
+    let timestamp = now()?;
+
+    // Check offer validity
+    if offer.expires_at <= timestamp {
+        return err!(ErrorCode::OfferExpired);
+    }
+    if nft.is_rented(timestamp) {
+        return err!(ErrorCode::NftRented);
+    }
+    // Offers bypass the owner terms, not the collection guardrails
+    if !policy.rental_enabled {
+        return err!(ErrorCode::CollectionRentalDisabled);
+    }
+    policy.check_listing(offer.days, offer.price, &Pubkey::default())?;
+    // The access policies may have changed since the offer
+    check_access(
+        &nft.access,
+        &nft.key(),
+        &offer.renter,
+        &ctx.accounts.nft_access,
+        &nft_proof,
+        ctx.program_id,
+    )?;
+    check_access(
+        &ctx.accounts.collection.access,
+        &nft.collection,
+        &offer.renter,
+        &ctx.accounts.collection_access,
+        &collection_proof,
+        ctx.program_id,
+    )?;
+    let rented_until = rental_end(timestamp, offer.days)?;
+    let amount = rental_amount(offer.price, offer.days)?;
+    check_not_booked(&ctx.accounts.bookings, timestamp, rented_until)?;
+
+    // Pay the owner from escrow, the offer rent goes back to the renter
+    let delegation_accounts = if offer.payment_mint == Pubkey::default() {
+        transfer_escrow(&offer.to_account_info(), &owner.to_account_info(), amount)?;
+        ctx.remaining_accounts
+    } else {
+        let bump = *ctx.bumps.get("offer").unwrap();
+        withdraw_from_vault(offer, bump, ctx.remaining_accounts, &owner.key(), amount)?;
+        ctx.remaining_accounts.get(4..).unwrap_or(&[])
+    };
+
+    // Rent nft
+    nft.start_rental(
+        &mut ctx.accounts.collection,
+        offer.renter,
+        rented_until,
+        offer.days,
+        amount,
+    )?;
+    ctx.accounts
+        .record
+        .start(nft, owner.key(), timestamp, amount);
+    sync_rented_metadata(
+        &ctx.accounts.collection,
+        &ctx.accounts.metadata,
+        &ctx.accounts.token_metadata_program,
+        &nft.mint,
+        true,
+        ctx.program_id,
+    )?;
+    // Delegated nfts are frozen in the owner wallet
+    if nft.token_account != Pubkey::default() {
+        freeze_rented(nft, delegation_accounts, ctx.program_id)?;
+    }
+    Ok(())
+}
+
+// Remaining accounts, for spl payment mints: [renter token account, offer
+// vault, token program, payment mint]
+pub fn cancel_rental_offer<'info>(
+    ctx: Context<'_, '_, '_, 'info, CancelRentalOffer<'info>>,
+) -> Result<()> {
+    let offer = &ctx.accounts.offer;
+    // Closing the offer refunds the lamports escrow, the vault one is paid
+    // back to the renter token account
+    if offer.payment_mint == Pubkey::default() {
+        return Ok(());
+    }
+    let bump = *ctx.bumps.get("offer").unwrap();
+    withdraw_from_vault(
+        offer,
+        bump,
+        ctx.remaining_accounts,
+        &offer.renter,
+        rental_amount(offer.price, offer.days)?,
+    )
+}
+
+// Pays `amount` out of the offer vault to the token account of `owner`,
+// `accounts` being [token account, offer vault, token program, payment mint]
+fn withdraw_from_vault<'info>(
+    offer: &Account<'info, RentalOffer>,
+    bump: u8,
+    accounts: &[AccountInfo<'info>],
+    owner: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let (to, vault_accounts) = accounts
+        .split_first()
+        .ok_or(ErrorCode::MissingPaymentAccounts)?;
+    let offer_info = offer.to_account_info();
+    let seeds: &[&[u8]] = &[
+        b"offer".as_ref(),
+        offer.mint.as_ref(),
+        offer.renter.as_ref(),
+        &[bump],
+    ];
+    let vault = Vault::from_accounts(vault_accounts, &offer_info.key(), &offer.payment_mint)?;
+    vault.withdraw(&offer_info, seeds, to, owner, amount)
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct CreateRentalOffer<'info> {
+    #[account(
+        init, payer = renter,
+        space = RentalOffer::SIZE,
+        seeds = [b"offer".as_ref(), mint.as_ref(), renter.key.as_ref()],
+        bump)]
+    pub offer: Account<'info, RentalOffer>,
+    #[account(
+        seeds = [b"nft".as_ref(), mint.as_ref()],
+        bump)]
+    pub nft: Account<'info, Nft>,
+    #[account(address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptRentalOffer<'info> {
+    #[account(mut,
+        seeds = [b"offer".as_ref(), nft.mint.as_ref(), renter.key.as_ref()],
+        bump,
+        has_one = renter,
+        close = renter)]
+    pub offer: Account<'info, RentalOffer>,
+    #[account(mut, has_one = owner)]
+    pub nft: Account<'info, Nft>,
+    #[account(mut, address = nft.collection)]
+    pub collection: Account<'info, Collection>,
+    /// CHECK: checked against the nft access policy
+    pub nft_access: UncheckedAccount<'info>,
+    /// CHECK: checked against the collection access policy
+    pub collection_access: UncheckedAccount<'info>,
+    /// CHECK: bookings pda, may not be initialized
+    #[account(
+        seeds = [b"bookings".as_ref(), nft.mint.as_ref()],
+        bump)]
+    pub bookings: UncheckedAccount<'info>,
+    #[account(
+        init, payer = owner,
+        space = RentalRecord::SIZE,
+        seeds = [b"rental_record".as_ref(), nft.mint.as_ref(), nft.rental_count.to_le_bytes().as_ref()],
+        bump)]
+    pub record: Account<'info, RentalRecord>,
+    /// CHECK: metadata of the nft, checked when the collection has rented metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: checked when the collection has rented metadata
+    pub token_metadata_program: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// CHECK: checked against the offer renter
+    #[account(mut)]
+    pub renter: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump,
+        constraint = !config.paused @ ErrorCode::ProgramPaused)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRentalOffer<'info> {
+    #[account(mut,
+        seeds = [b"offer".as_ref(), offer.mint.as_ref(), renter.key.as_ref()],
+        bump,
+        has_one = renter,
+        close = renter)]
+    pub offer: Account<'info, RentalOffer>,
+    #[account(mut)]
+    pub renter: Signer<'info>,
+}
+
+#[account]
+pub struct RentalOffer {
+    pub mint: Pubkey,
+    pub renter: Pubkey,
+    // Payment mint of the escrow, the nft one when the offer was made
+    pub payment_mint: Pubkey,
+    // Proposed terms, escrowed in full
+    pub price: u64,
+    pub days: u32,
+    pub expires_at: i64,
+}
+
+impl RentalOffer {
+    pub const SIZE: usize = 8   // discriminator
+    + 32 // mint
+    + 32 // renter
+    + 32 // payment_mint
+    + 8 // price
+    + 4 // days
+    + 8; // expires_at
+}
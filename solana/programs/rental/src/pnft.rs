@@ -0,0 +1,307 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::sysvar;
+use mpl_token_metadata::state::PREFIX;
+
+use crate::ErrorCode;
+
+// Programmable nfts (pNFT) stay frozen by their edition, delegation and
+// locking going through the token metadata program. The token metadata crate
+// in use predates them, their instructions are built here
+
+// TokenStandard::ProgrammableNonFungible & ProgrammableNonFungibleEdition
+const PROGRAMMABLE_STANDARDS: [u8; 2] = [4, 5];
+// MetadataInstruction variants
+const DELEGATE: u8 = 44;
+const REVOKE: u8 = 45;
+const LOCK: u8 = 46;
+const UNLOCK: u8 = 47;
+// DelegateArgs::UtilityV1 & RevokeArgs::UtilityV1
+const UTILITY_V1: u8 = 4;
+// LockArgs::V1 & UnlockArgs::V1
+const LOCK_V1: u8 = 0;
+// Token record state, after its key & bump
+const TOKEN_STATE_OFFSET: usize = 2;
+const TOKEN_STATE_LOCKED: u8 = 1;
+
+// Token metadata accounts of a programmable nft
+pub struct PnftAccounts<'a, 'info> {
+    pub token_metadata_program: &'a AccountInfo<'info>,
+    pub metadata: &'a AccountInfo<'info>,
+    pub edition: &'a AccountInfo<'info>,
+    pub mint: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub token_record: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub sysvar_instructions: &'a AccountInfo<'info>,
+    // The token metadata program in place of both when the nft has no rule set
+    pub authorization_rules_program: &'a AccountInfo<'info>,
+    pub authorization_rules: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> PnftAccounts<'a, 'info> {
+    // Accounts from the named ones and `extra`: [token record, system program,
+    // sysvar instructions, authorization rules program, authorization rules]
+    pub fn new(
+        token_metadata_program: &'a AccountInfo<'info>,
+        metadata: &'a AccountInfo<'info>,
+        edition: &'a AccountInfo<'info>,
+        mint: &'a AccountInfo<'info>,
+        token_account: &'a AccountInfo<'info>,
+        token_program: &'a AccountInfo<'info>,
+        extra: &'a [AccountInfo<'info>],
+    ) -> Result<Self> {
+        if extra.len() < 5 {
+            return err!(ErrorCode::InvalidDelegation);
+        }
+        let token_record = token_record_key(
+            &mint.key(),
+            &token_account.key(),
+            &token_metadata_program.key(),
+        );
+        if extra[0].key() != token_record
+            || extra[1].key() != System::id()
+            || extra[2].key() != sysvar::instructions::ID
+        {
+            return err!(ErrorCode::InvalidDelegation);
+        }
+        Ok(PnftAccounts {
+            token_metadata_program,
+            metadata,
+            edition,
+            mint,
+            token_account,
+            token_record: &extra[0],
+            token_program,
+            system_program: &extra[1],
+            sysvar_instructions: &extra[2],
+            authorization_rules_program: &extra[3],
+            authorization_rules: &extra[4],
+        })
+    }
+
+    fn infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![
+            self.token_metadata_program.to_owned(),
+            self.metadata.to_owned(),
+            self.edition.to_owned(),
+            self.mint.to_owned(),
+            self.token_account.to_owned(),
+            self.token_record.to_owned(),
+            self.token_program.to_owned(),
+            self.system_program.to_owned(),
+            self.sysvar_instructions.to_owned(),
+            self.authorization_rules_program.to_owned(),
+            self.authorization_rules.to_owned(),
+        ]
+    }
+
+    // Trailing accounts shared by the instructions
+    fn program_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(self.system_program.key(), false),
+            AccountMeta::new_readonly(self.sysvar_instructions.key(), false),
+            AccountMeta::new_readonly(self.token_program.key(), false),
+            AccountMeta::new_readonly(self.authorization_rules_program.key(), false),
+            AccountMeta::new_readonly(self.authorization_rules.key(), false),
+        ]
+    }
+
+    // Makes `delegate` the utility delegate of the nft, `owner` signing
+    pub fn delegate(
+        &self,
+        delegate: &AccountInfo<'info>,
+        owner: &AccountInfo<'info>,
+    ) -> Result<()> {
+        let mut data = vec![DELEGATE, UTILITY_V1];
+        data.extend_from_slice(&1u64.to_le_bytes());
+        // No authorization data
+        data.push(0);
+        self.invoke(
+            data,
+            self.delegate_metas(delegate, owner),
+            &[delegate, owner],
+            &[],
+        )
+    }
+
+    // Revokes the utility delegate, `owner` signing
+    pub fn revoke(&self, delegate: &AccountInfo<'info>, owner: &AccountInfo<'info>) -> Result<()> {
+        let data = vec![REVOKE, UTILITY_V1];
+        self.invoke(
+            data,
+            self.delegate_metas(delegate, owner),
+            &[delegate, owner],
+            &[],
+        )
+    }
+
+    // Locks the nft in the owner wallet, the delegate signing with `signer_seeds`
+    pub fn lock(&self, delegate: &AccountInfo<'info>, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let data = vec![LOCK, LOCK_V1, 0];
+        self.invoke(data, self.lock_metas(delegate), &[delegate], signer_seeds)
+    }
+
+    // Unlocks a nft locked with `lock`
+    pub fn unlock(&self, delegate: &AccountInfo<'info>, signer_seeds: &[&[&[u8]]]) -> Result<()> {
+        let data = vec![UNLOCK, LOCK_V1, 0];
+        self.invoke(data, self.lock_metas(delegate), &[delegate], signer_seeds)
+    }
+
+    pub fn is_locked(&self) -> Result<bool> {
+        let data = self.token_record.try_borrow_data()?;
+        Ok(data.get(TOKEN_STATE_OFFSET) == Some(&TOKEN_STATE_LOCKED))
+    }
+
+    fn delegate_metas(&self, delegate: &AccountInfo, owner: &AccountInfo) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            // No delegate record for token delegates
+            AccountMeta::new_readonly(self.token_metadata_program.key(), false),
+            AccountMeta::new_readonly(delegate.key(), false),
+            AccountMeta::new(self.metadata.key(), false),
+            AccountMeta::new_readonly(self.edition.key(), false),
+            AccountMeta::new(self.token_record.key(), false),
+            AccountMeta::new_readonly(self.mint.key(), false),
+            AccountMeta::new(self.token_account.key(), false),
+            // Authority & payer
+            AccountMeta::new_readonly(owner.key(), true),
+            AccountMeta::new(owner.key(), true),
+        ];
+        metas.extend(self.program_metas());
+        metas
+    }
+
+    fn lock_metas(&self, delegate: &AccountInfo) -> Vec<AccountMeta> {
+        let mut metas = vec![
+            AccountMeta::new_readonly(delegate.key(), true),
+            // No token owner for delegates
+            AccountMeta::new_readonly(self.token_metadata_program.key(), false),
+            AccountMeta::new(self.token_account.key(), false),
+            AccountMeta::new_readonly(self.mint.key(), false),
+            AccountMeta::new(self.metadata.key(), false),
+            AccountMeta::new_readonly(self.edition.key(), false),
+            AccountMeta::new(self.token_record.key(), false),
+            // Payer, nothing gets created
+            AccountMeta::new(delegate.key(), true),
+        ];
+        metas.extend(self.program_metas());
+        metas
+    }
+
+    fn invoke(
+        &self,
+        data: Vec<u8>,
+        accounts: Vec<AccountMeta>,
+        signers: &[&AccountInfo<'info>],
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        let mut infos = self.infos();
+        infos.extend(signers.iter().map(|signer| (*signer).clone()));
+        invoke_signed(
+            &Instruction {
+                program_id: self.token_metadata_program.key(),
+                accounts,
+                data,
+            },
+            &infos,
+            signer_seeds,
+        )?;
+        Ok(())
+    }
+}
+
+// Whether `metadata` is the metadata of a programmable nft
+pub fn is_programmable(metadata: &AccountInfo) -> Result<bool> {
+    let data = metadata.try_borrow_data()?;
+    Ok(token_standard(&data).map_or(false, |standard| PROGRAMMABLE_STANDARDS.contains(&standard)))
+}
+
+pub fn token_record_key(
+    mint: &Pubkey,
+    token_account: &Pubkey,
+    token_metadata_program: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            token_metadata_program.as_ref(),
+            mint.as_ref(),
+            b"token_record".as_ref(),
+            token_account.as_ref(),
+        ],
+        token_metadata_program,
+    )
+    .0
+}
+
+// Token standard of raw metadata, walking the fields before it
+fn token_standard(data: &[u8]) -> Option<u8> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    };
+    // Key, update authority, mint
+    let mut offset = 1 + 32 + 32;
+    // Name, symbol, uri
+    for _ in 0..3 {
+        offset += 4 + read_u32(offset)?;
+    }
+    // Seller fee basis points
+    offset += 2;
+    // Creators of 34 bytes each
+    if *data.get(offset)? == 1 {
+        offset += 4 + 34 * read_u32(offset + 1)?;
+    }
+    offset += 1;
+    // Primary sale happened, is mutable
+    offset += 2;
+    // Edition nonce
+    if *data.get(offset)? == 1 {
+        offset += 1;
+    }
+    offset += 1;
+    match *data.get(offset)? {
+        1 => data.get(offset + 1).copied(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Metadata up to the token standard, with `creators` creators
+    fn metadata(creators: u32, edition_nonce: bool, token_standard: Option<u8>) -> Vec<u8> {
+        let mut data = vec![4];
+        data.extend_from_slice(&[0; 64]);
+        for len in [32u32, 10, 200] {
+            data.extend_from_slice(&len.to_le_bytes());
+            data.extend(std::iter::repeat(0).take(len as usize));
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&creators.to_le_bytes());
+        data.extend(std::iter::repeat(1).take(34 * creators as usize));
+        data.extend_from_slice(&[0, 1]);
+        match edition_nonce {
+            true => data.extend_from_slice(&[1, 255]),
+            false => data.push(0),
+        }
+        match token_standard {
+            Some(standard) => data.extend_from_slice(&[1, standard]),
+            None => data.push(0),
+        }
+        data
+    }
+
+    #[test]
+    fn reads_token_standard() {
+        assert_eq!(token_standard(&metadata(1, true, Some(4))), Some(4));
+        assert_eq!(token_standard(&metadata(3, false, Some(0))), Some(0));
+        assert_eq!(token_standard(&metadata(2, true, None)), None);
+        // Truncated metadata
+        assert_eq!(token_standard(&metadata(2, true, Some(4))[..100]), None);
+    }
+}
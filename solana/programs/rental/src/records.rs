@@ -0,0 +1,210 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
+
+use crate::time::now;
+use crate::{ErrorCode, Nft};
+
+// Records are kept at least this long after the rental end
+pub const RECORD_RETENTION_S: i64 = 30 * 24 * 3600;
+
+pub fn close_rental_record(ctx: Context<CloseRentalRecord>) -> Result<()> {
+    let timestamp = now()?;
+    let retained_until = ctx
+        .accounts
+        .record
+        .end
+        .checked_add(RECORD_RETENTION_S)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if timestamp < retained_until {
+        return err!(ErrorCode::RecordRetained);
+    }
+    Ok(())
+}
+
+// Records the rental the nft just started outside of an anchor `init`,
+// `record_info` being the record pda of the rental
+pub fn create_rental_record<'info>(
+    record_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    nft: &Nft,
+    start: i64,
+    amount: u64,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let index = nft
+        .rental_count
+        .checked_sub(1)
+        .ok_or(ErrorCode::MathOverflow)?
+        .to_le_bytes();
+    let (pda, bump) = Pubkey::find_program_address(
+        &[b"rental_record".as_ref(), nft.mint.as_ref(), index.as_ref()],
+        program_id,
+    );
+    if pda != record_info.key() {
+        return err!(ErrorCode::InvalidRecordAccount);
+    }
+    create_pda_account(
+        record_info,
+        payer,
+        system_program,
+        RentalRecord::SIZE,
+        &[
+            b"rental_record".as_ref(),
+            nft.mint.as_ref(),
+            index.as_ref(),
+            &[bump],
+        ],
+        program_id,
+    )?;
+    let mut record = RentalRecord::default();
+    record.start(nft, payer.key(), start, amount);
+    record.try_serialize(&mut &mut record_info.try_borrow_mut_data()?[..])
+}
+
+// Creates the program account at the `seeds` pda, the way an anchor `init`
+// does: an address already holding lamports can't be created, it is topped
+// up to rent exemption then allocated and assigned instead
+pub fn create_pda_account<'info>(
+    account_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    seeds: &[&[u8]],
+    program_id: &Pubkey,
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+    let balance = account_info.lamports();
+    if balance == 0 {
+        let cpi_accounts = CreateAccount {
+            from: payer.to_owned(),
+            to: account_info.to_owned(),
+        };
+        return create_account(
+            CpiContext::new_with_signer(system_program.to_owned(), cpi_accounts, &[seeds]),
+            lamports,
+            space as u64,
+            program_id,
+        );
+    }
+
+    let top_up = lamports.saturating_sub(balance);
+    if top_up > 0 {
+        let cpi_accounts = Transfer {
+            from: payer.to_owned(),
+            to: account_info.to_owned(),
+        };
+        transfer(
+            CpiContext::new(system_program.to_owned(), cpi_accounts),
+            top_up,
+        )?;
+    }
+    let cpi_accounts = Allocate {
+        account_to_allocate: account_info.to_owned(),
+    };
+    allocate(
+        CpiContext::new_with_signer(system_program.to_owned(), cpi_accounts, &[seeds]),
+        space as u64,
+    )?;
+    let cpi_accounts = Assign {
+        account_to_assign: account_info.to_owned(),
+    };
+    assign(
+        CpiContext::new_with_signer(system_program.to_owned(), cpi_accounts, &[seeds]),
+        program_id,
+    )
+}
+
+// Marks the rental record as terminated, if still around
+pub fn terminate_rental_record(
+    record_info: &AccountInfo,
+    reason: TerminationReason,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if record_info.data_is_empty() {
+        return Ok(());
+    }
+    let mut record = Account::<RentalRecord>::try_from(record_info)?;
+    if record.reason == TerminationReason::Ongoing {
+        record.reason = reason;
+        record.exit(program_id)?;
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseRentalRecord<'info> {
+    #[account(mut,
+        seeds = [b"rental_record".as_ref(), record.mint.as_ref(), record.index.to_le_bytes().as_ref()],
+        bump,
+        has_one = payer,
+        close = payer)]
+    pub record: Account<'info, RentalRecord>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum TerminationReason {
+    // Running, or over but not released yet
+    Ongoing,
+    // Released once its term was over
+    Expired,
+    // Nft delisted once the rental was over
+    Delisted,
+}
+
+impl Default for TerminationReason {
+    fn default() -> Self {
+        TerminationReason::Ongoing
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct RentalRecord {
+    pub mint: Pubkey,
+    // Rental count of the nft when rented
+    pub index: u64,
+    pub renter: Pubkey,
+    // Refunded the record rent once closed
+    pub payer: Pubkey,
+    pub start: i64,
+    pub end: i64,
+    pub amount: u64,
+    // Pubkey::default() for SOL
+    pub payment_mint: Pubkey,
+    pub reason: TerminationReason,
+    // Rental pass minted for the rental, Pubkey::default() if none
+    pub pass_mint: Pubkey,
+}
+
+impl RentalRecord {
+    pub const SIZE: usize = 8   // discriminator
+    + 32 // mint
+    + 8 // index
+    + 32 // renter
+    + 32 // payer
+    + 8 // start
+    + 8 // end
+    + 8 // amount
+    + 32 // payment_mint
+    + 1 // reason
+    + 32; // pass_mint
+
+    // Records the rental the nft just started, paid `amount` by `payer`
+    pub fn start(&mut self, nft: &Nft, payer: Pubkey, start: i64, amount: u64) {
+        self.mint = nft.mint;
+        self.index = nft.rental_count.saturating_sub(1);
+        self.renter = nft.renter;
+        self.payer = payer;
+        self.start = start;
+        self.end = nft.rented_until;
+        self.amount = amount;
+        self.payment_mint = nft.payment_mint;
+        self.reason = TerminationReason::Ongoing;
+        self.pass_mint = Pubkey::default();
+    }
+}
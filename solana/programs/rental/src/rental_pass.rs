@@ -0,0 +1,259 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program::{create_account, CreateAccount};
+use anchor_spl::associated_token::{self, Create};
+use anchor_spl::token::spl_token::state::Mint as MintState;
+use anchor_spl::token::{
+    self, Approve, Burn, FreezeAccount, InitializeMint, Mint, MintTo, ThawAccount, Token,
+    TokenAccount,
+};
+
+use crate::nft_utils::{create_metadata, TOKEN_METADATA_PROGRAM_ID};
+use crate::records::{create_pda_account, RentalRecord};
+use crate::time::now;
+use crate::{ErrorCode, Nft};
+
+const PASS_NAME: &str = "Rental pass";
+const PASS_SYMBOL: &str = "PASS";
+const URI_MAX_LEN: usize = 200;
+
+// Mints a frozen rental pass to the renter of `nft`, whose metadata uri
+// points back to the rented mint and the rental expiry. Minted by rent_nft
+// along the rental it starts, one pass per rental tracked on its record.
+// Accounts: [rental pass, pass mint, pass token account, pass metadata,
+// pass authority, token program, associated token program, rent], the pass
+// mint being a fresh keypair signing the transaction. Passes are Token mints
+// kept non-transferable by the freeze, rather than Token-2022 mints with the
+// non-transferable extension: token metadata 1.2.7 only creates metadata for
+// Token mints
+#[allow(clippy::too_many_arguments)]
+pub fn mint_rental_pass<'info>(
+    nft: &Nft,
+    record: &mut RentalRecord,
+    renter: &AccountInfo<'info>,
+    token_metadata_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+    base_uri: String,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if accounts.len() < 8 {
+        return err!(ErrorCode::InvalidPassAccounts);
+    }
+    let rental_pass = &accounts[0];
+    let pass_mint = &accounts[1];
+    let pass_token_account = &accounts[2];
+    let pass_metadata = &accounts[3];
+    let pass_authority = &accounts[4];
+    let token_program = &accounts[5];
+    let associated_token_program = &accounts[6];
+    let rent = &accounts[7];
+
+    let (pass_pda, pass_bump) = Pubkey::find_program_address(
+        &[b"rental_pass".as_ref(), pass_mint.key.as_ref()],
+        program_id,
+    );
+    let (authority_pda, bump) =
+        Pubkey::find_program_address(&[b"pass_authority".as_ref()], program_id);
+    if pass_pda != rental_pass.key()
+        || authority_pda != pass_authority.key()
+        || !pass_mint.is_signer
+        || token_program.key() != token::ID
+        || associated_token_program.key() != associated_token::ID
+        || rent.key() != sysvar::rent::ID
+        || token_metadata_program.key() != TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap()
+    {
+        return err!(ErrorCode::InvalidPassAccounts);
+    }
+    let uri = format!("{}?mint={}&until={}", base_uri, nft.mint, nft.rented_until);
+    require!(uri.len() <= URI_MAX_LEN, ErrorCode::StringTooLong);
+
+    let seeds: &[&[u8]] = &[b"pass_authority".as_ref(), &[bump]];
+    let rent_exemption = Rent::get()?;
+
+    // Fresh mint for every pass, and its renter token account
+    let cpi_accounts = CreateAccount {
+        from: renter.to_owned(),
+        to: pass_mint.to_owned(),
+    };
+    create_account(
+        CpiContext::new(system_program.to_owned(), cpi_accounts),
+        rent_exemption.minimum_balance(MintState::LEN),
+        MintState::LEN as u64,
+        &token::ID,
+    )?;
+    let cpi_accounts = InitializeMint {
+        mint: pass_mint.to_owned(),
+        rent: rent.to_owned(),
+    };
+    token::initialize_mint(
+        CpiContext::new(token_program.to_owned(), cpi_accounts),
+        0,
+        pass_authority.key,
+        Some(pass_authority.key),
+    )?;
+    let cpi_accounts = Create {
+        payer: renter.to_owned(),
+        associated_token: pass_token_account.to_owned(),
+        authority: renter.to_owned(),
+        mint: pass_mint.to_owned(),
+        system_program: system_program.to_owned(),
+        token_program: token_program.to_owned(),
+        rent: rent.to_owned(),
+    };
+    associated_token::create(CpiContext::new(
+        associated_token_program.to_owned(),
+        cpi_accounts,
+    ))?;
+
+    // Mint the pass
+    let cpi_accounts = MintTo {
+        mint: pass_mint.to_owned(),
+        to: pass_token_account.to_owned(),
+        authority: pass_authority.to_owned(),
+    };
+    token::mint_to(
+        CpiContext::new_with_signer(token_program.to_owned(), cpi_accounts, &[seeds]),
+        1,
+    )?;
+    create_metadata(
+        token_metadata_program,
+        pass_metadata,
+        pass_mint,
+        pass_authority,
+        renter,
+        pass_authority,
+        system_program,
+        rent,
+        PASS_NAME.to_string(),
+        PASS_SYMBOL.to_string(),
+        uri,
+        None,
+        0,
+        None,
+        false,
+        &[seeds],
+    )?;
+
+    // Let the program burn the pass once expired
+    let cpi_accounts = Approve {
+        to: pass_token_account.to_owned(),
+        delegate: pass_authority.to_owned(),
+        authority: renter.to_owned(),
+    };
+    token::approve(CpiContext::new(token_program.to_owned(), cpi_accounts), 1)?;
+
+    // Freeze it so it can't be transferred
+    let cpi_accounts = FreezeAccount {
+        account: pass_token_account.to_owned(),
+        mint: pass_mint.to_owned(),
+        authority: pass_authority.to_owned(),
+    };
+    token::freeze_account(CpiContext::new_with_signer(
+        token_program.to_owned(),
+        cpi_accounts,
+        &[seeds],
+    ))?;
+
+    // Track the pass, burnable by anyone once the rental is over
+    create_pda_account(
+        rental_pass,
+        renter,
+        system_program,
+        RentalPass::SIZE,
+        &[
+            b"rental_pass".as_ref(),
+            pass_mint.key.as_ref(),
+            &[pass_bump],
+        ],
+        program_id,
+    )?;
+    let pass = RentalPass {
+        mint: nft.mint,
+        renter: renter.key(),
+        pass_mint: pass_mint.key(),
+        expires_at: nft.rented_until,
+    };
+    pass.try_serialize(&mut &mut rental_pass.try_borrow_mut_data()?[..])?;
+    record.pass_mint = pass_mint.key();
+    Ok(())
+}
+
+// Burns an expired pass, anyone can crank it
+pub fn burn_expired_pass(ctx: Context<BurnExpiredPass>) -> Result<()> {
+    let pass_authority = &ctx.accounts.pass_authority;
+    let token_program = &ctx.accounts.token_program;
+
+    let timestamp = now()?;
+    if timestamp < ctx.accounts.rental_pass.expires_at {
+        return err!(ErrorCode::PassNotExpired);
+    }
+
+    let bump = *ctx.bumps.get("pass_authority").unwrap();
+    let seeds: &[&[u8]] = &[b"pass_authority".as_ref(), &[bump]];
+
+    let cpi_accounts = ThawAccount {
+        account: ctx.accounts.pass_token_account.to_account_info(),
+        mint: ctx.accounts.pass_mint.to_account_info(),
+        authority: pass_authority.to_account_info(),
+    };
+    token::thaw_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        cpi_accounts,
+        &[seeds],
+    ))?;
+    let cpi_accounts = Burn {
+        mint: ctx.accounts.pass_mint.to_account_info(),
+        to: ctx.accounts.pass_token_account.to_account_info(),
+        authority: pass_authority.to_account_info(),
+    };
+    token::burn(
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, &[seeds]),
+        1,
+    )?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct BurnExpiredPass<'info> {
+    #[account(mut,
+        seeds = [b"rental_pass".as_ref(), pass_mint.key().as_ref()],
+        bump,
+        has_one = pass_mint,
+        has_one = renter,
+        close = renter)]
+    pub rental_pass: Account<'info, RentalPass>,
+    #[account(mut)]
+    pub pass_mint: Account<'info, Mint>,
+    #[account(mut,
+        associated_token::mint = pass_mint,
+        associated_token::authority = renter)]
+    pub pass_token_account: Account<'info, TokenAccount>,
+    /// CHECK: checked against the pass renter, refunded the pass account rent
+    #[account(mut)]
+    pub renter: AccountInfo<'info>,
+    /// CHECK: pda signing for the pass mint
+    #[account(
+        seeds = [b"pass_authority".as_ref()],
+        bump)]
+    pub pass_authority: UncheckedAccount<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[account]
+pub struct RentalPass {
+    // Rented mint
+    pub mint: Pubkey,
+    pub renter: Pubkey,
+    pub pass_mint: Pubkey,
+    pub expires_at: i64,
+}
+
+impl RentalPass {
+    const SIZE: usize = 8   // discriminator
+    + 32 // mint
+    + 32 // renter
+    + 32 // pass_mint
+    + 8; // expires_at
+}
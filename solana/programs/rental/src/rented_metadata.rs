@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+use mpl_token_metadata::state::{Metadata, MAX_URI_LENGTH, PREFIX};
+
+use crate::nft_utils::{metadata_data, update_metadata, TOKEN_METADATA_PROGRAM_ID};
+use crate::{Collection, ErrorCode};
+
+// Query marking the metadata uri of a rented nft, for the metadata server
+// to serve the rented variant
+const RENTED_QUERY: &str = "rented=1";
+
+// Opts the collection in or out of rented metadata. The collection pda must
+// be the update authority of its nfts, as for nfts minted under it
+pub fn set_rented_metadata(
+    ctx: Context<SetRentedMetadata>,
+    _symbol: String,
+    enabled: bool,
+) -> Result<()> {
+    ctx.accounts.collection.rented_metadata = enabled;
+    Ok(())
+}
+
+// Swaps the metadata uri of `mint` to its rented variant, or back, for
+// collections opted in. `metadata` & `token_metadata_program` are not read
+// otherwise and may be any account
+pub fn sync_rented_metadata<'info>(
+    collection: &Account<'info, Collection>,
+    metadata: &AccountInfo<'info>,
+    token_metadata_program: &AccountInfo<'info>,
+    mint: &Pubkey,
+    rented: bool,
+    program_id: &Pubkey,
+) -> Result<()> {
+    if !collection.rented_metadata {
+        return Ok(());
+    }
+    if token_metadata_program.key() != TOKEN_METADATA_PROGRAM_ID.parse::<Pubkey>().unwrap() {
+        return err!(ErrorCode::InvalidMetadata);
+    }
+    let (metadata_key, _) = Pubkey::find_program_address(
+        &[
+            PREFIX.as_bytes(),
+            token_metadata_program.key.as_ref(),
+            mint.as_ref(),
+        ],
+        token_metadata_program.key,
+    );
+    if metadata_key != metadata.key() {
+        return err!(ErrorCode::InvalidMetadata);
+    }
+    let metadata_state =
+        Metadata::from_account_info(metadata).map_err(|_| error!(ErrorCode::InvalidMetadata))?;
+    // Locked or not managed by the collection, left as is
+    if !metadata_state.is_mutable || metadata_state.update_authority != collection.key() {
+        return Ok(());
+    }
+
+    let mut data = metadata_data(&metadata_state);
+    let uri = rented_uri(&data.uri, rented);
+    if uri == data.uri {
+        return Ok(());
+    }
+    require!(uri.len() <= MAX_URI_LENGTH, ErrorCode::StringTooLong);
+    data.uri = uri;
+
+    let (_, bump) = Pubkey::find_program_address(
+        &[
+            b"collection".as_ref(),
+            collection.symbol.as_ref(),
+            collection.authority.as_ref(),
+        ],
+        program_id,
+    );
+    update_metadata(
+        token_metadata_program,
+        metadata,
+        &collection.to_account_info(),
+        Some(data),
+        None,
+        &[&[
+            b"collection".as_ref(),
+            collection.symbol.as_ref(),
+            collection.authority.as_ref(),
+            &[bump],
+        ]],
+    )
+}
+
+// `uri` with the rented query added or removed
+pub fn rented_uri(uri: &str, rented: bool) -> String {
+    let base = uri
+        .strip_suffix(RENTED_QUERY)
+        .and_then(|base| base.strip_suffix('?').or_else(|| base.strip_suffix('&')))
+        .unwrap_or(uri);
+    if !rented {
+        return base.to_string();
+    }
+    let separator = if base.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", base, separator, RENTED_QUERY)
+}
+
+#[derive(Accounts)]
+#[instruction(_symbol: String)]
+pub struct SetRentedMetadata<'info> {
+    #[account(mut,
+        seeds = [b"collection".as_ref(), _symbol.as_ref(), authority.key.as_ref()],
+        bump)]
+    pub collection: Account<'info, Collection>,
+    pub authority: Signer<'info>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rented_uri_round_trips() {
+        let uri = "https://arweave.net/abc";
+        assert_eq!(rented_uri(uri, true), "https://arweave.net/abc?rented=1");
+        assert_eq!(rented_uri(&rented_uri(uri, true), false), uri);
+        assert_eq!(
+            rented_uri(&rented_uri(uri, true), true),
+            rented_uri(uri, true)
+        );
+        assert_eq!(rented_uri(uri, false), uri);
+    }
+
+    #[test]
+    fn rented_uri_keeps_queries() {
+        let uri = "https://api.example.com/nft?id=1";
+        assert_eq!(
+            rented_uri(uri, true),
+            "https://api.example.com/nft?id=1&rented=1"
+        );
+        assert_eq!(rented_uri(&rented_uri(uri, true), false), uri);
+    }
+}
@@ -0,0 +1,7 @@
+use anchor_lang::prelude::*;
+
+// Time source of the rental logic. It reads the Clock sysvar, which tests
+// warp through solana-program-test to check expiries without waiting
+pub fn now() -> Result<i64> {
+    Ok(Clock::get()?.unix_timestamp)
+}
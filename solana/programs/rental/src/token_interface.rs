@@ -0,0 +1,214 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_spl::token;
+use anchor_spl::token::spl_token::state::{Account as TokenState, AccountState, Mint as MintState};
+
+use crate::ErrorCode;
+
+// Token accounts & mints of both the Token and Token-2022 programs. Their base
+// layout is shared, Token-2022 appending its extensions after the account type
+
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+
+// TokenInstruction::TransferChecked, same in both programs
+const TRANSFER_CHECKED: u8 = 12;
+// Extended mints are padded to the token account size, before the account type
+const ACCOUNT_TYPE_OFFSET: usize = TokenState::LEN;
+// ExtensionType::TransferFeeConfig
+const TRANSFER_FEE_CONFIG: u16 = 1;
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+
+pub fn is_token_program(key: &Pubkey) -> bool {
+    *key == token::ID || *key == TOKEN_2022_PROGRAM_ID.parse::<Pubkey>().unwrap()
+}
+
+// Nft delegation & edition prints still go through the Token program only,
+// Token-2022 nft accounts being rejected explicitly
+pub fn require_token_program(info: &AccountInfo) -> Result<()> {
+    if *info.owner == token::ID {
+        return Ok(());
+    }
+    if *info.owner == TOKEN_2022_PROGRAM_ID.parse::<Pubkey>().unwrap() {
+        return err!(ErrorCode::UnsupportedTokenProgram);
+    }
+    err!(ErrorCode::InvalidTokenProgram)
+}
+
+// Base state of a token account of either token program
+pub fn token_account(info: &AccountInfo) -> Result<TokenState> {
+    if !is_token_program(info.owner) {
+        return err!(ErrorCode::InvalidTokenProgram);
+    }
+    let data = info.try_borrow_data()?;
+    let state = data
+        .get(..TokenState::LEN)
+        .and_then(|base| TokenState::unpack_from_slice(base).ok())
+        .ok_or(ErrorCode::InvalidTokenAccount)?;
+    if state.state == AccountState::Uninitialized {
+        return err!(ErrorCode::InvalidTokenAccount);
+    }
+    Ok(state)
+}
+
+// Base state of a mint of either token program
+pub fn mint(info: &AccountInfo) -> Result<MintState> {
+    if !is_token_program(info.owner) {
+        return err!(ErrorCode::InvalidTokenProgram);
+    }
+    let data = info.try_borrow_data()?;
+    let state = data
+        .get(..MintState::LEN)
+        .and_then(|base| MintState::unpack_from_slice(base).ok())
+        .ok_or(ErrorCode::InvalidMint)?;
+    if !state.is_initialized {
+        return err!(ErrorCode::InvalidMint);
+    }
+    Ok(state)
+}
+
+// Transfer fee to add to `amount` for the recipient to receive `amount`,
+// zero without the transfer fee extension
+pub fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+    let data = mint.try_borrow_data()?;
+    let config = match extension(&data, TRANSFER_FEE_CONFIG) {
+        Some(config) => config,
+        None => return Ok(0),
+    };
+    // Authorities & withheld amount, then the older & newer fees
+    let fees = config.get(72..108).ok_or(ErrorCode::InvalidMint)?;
+    let newer = &fees[18..];
+    let epoch = Clock::get()?.epoch;
+    let fee = if epoch >= read_u64(newer, 0) {
+        newer
+    } else {
+        &fees[..18]
+    };
+    inverse_fee(
+        amount,
+        read_u64(fee, 8),
+        u16::from_le_bytes([fee[16], fee[17]]),
+    )
+    .ok_or_else(|| error!(ErrorCode::MathOverflow))
+}
+
+// Transfers `amount` of `mint` with the token program owning it, a pda
+// authority signing with `signer_seeds`
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'a>(
+    token_program: &AccountInfo<'a>,
+    from: &AccountInfo<'a>,
+    mint: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let mut data = vec![TRANSFER_CHECKED];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    let ix = Instruction {
+        program_id: token_program.key(),
+        accounts: vec![
+            AccountMeta::new(from.key(), false),
+            AccountMeta::new_readonly(mint.key(), false),
+            AccountMeta::new(to.key(), false),
+            AccountMeta::new_readonly(authority.key(), true),
+        ],
+        data,
+    };
+    invoke_signed(
+        &ix,
+        &[
+            from.to_owned(),
+            mint.to_owned(),
+            to.to_owned(),
+            authority.to_owned(),
+            token_program.to_owned(),
+        ],
+        signer_seeds,
+    )?;
+    Ok(())
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+// Value of the `extension_type` extension of a Token-2022 account, walking
+// its type-length-value entries
+fn extension(data: &[u8], extension_type: u16) -> Option<&[u8]> {
+    let mut offset = ACCOUNT_TYPE_OFFSET + 1;
+    while let Some(header) = data.get(offset..offset + 4) {
+        let kind = u16::from_le_bytes([header[0], header[1]]);
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        let value = data.get(offset + 4..offset + 4 + len)?;
+        if kind == extension_type {
+            return Some(value);
+        }
+        offset += 4 + len;
+    }
+    None
+}
+
+// Fee of the transfer leaving `amount` after fees, capped at `maximum_fee`
+fn inverse_fee(amount: u64, maximum_fee: u64, basis_points: u16) -> Option<u64> {
+    let basis_points = basis_points as u128;
+    if basis_points == 0 || amount == 0 {
+        return Some(0);
+    }
+    if basis_points >= MAX_FEE_BASIS_POINTS {
+        return Some(maximum_fee);
+    }
+    let gross = (amount as u128)
+        .checked_mul(MAX_FEE_BASIS_POINTS)?
+        .checked_add(MAX_FEE_BASIS_POINTS - basis_points - 1)?
+        / (MAX_FEE_BASIS_POINTS - basis_points);
+    let fee = u64::try_from(gross - amount as u128).ok()?;
+    Some(fee.min(maximum_fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Fee the token program withholds on a transfer of `amount`
+    fn fee(amount: u64, maximum_fee: u64, basis_points: u16) -> u64 {
+        let fee = (amount as u128 * basis_points as u128 + 9_999) / 10_000;
+        (fee as u64).min(maximum_fee)
+    }
+
+    #[test]
+    fn inverse_fee_leaves_amount() {
+        for amount in [1, 999, 1_000_000, 123_456_789] {
+            for basis_points in [1, 50, 250, 9_999] {
+                let inverse = inverse_fee(amount, u64::MAX, basis_points).unwrap();
+                assert_eq!(
+                    amount + inverse - fee(amount + inverse, u64::MAX, basis_points),
+                    amount
+                );
+            }
+        }
+        assert_eq!(inverse_fee(1_000_000, 5_000, 100), Some(5_000));
+        assert_eq!(inverse_fee(1_000_000, 5_000, 0), Some(0));
+    }
+
+    #[test]
+    fn finds_extensions() {
+        let mut data = vec![0; ACCOUNT_TYPE_OFFSET + 1];
+        data.extend_from_slice(&[3, 0, 2, 0, 7, 7]);
+        data.extend_from_slice(&[TRANSFER_FEE_CONFIG as u8, 0, 0, 0]);
+        assert_eq!(extension(&data, 3), Some(&[7, 7][..]));
+        assert_eq!(extension(&data, TRANSFER_FEE_CONFIG), Some(&[][..]));
+        assert_eq!(extension(&data, 9), None);
+        // Base token account, no extensions
+        assert_eq!(
+            extension(&data[..ACCOUNT_TYPE_OFFSET], TRANSFER_FEE_CONFIG),
+            None
+        );
+    }
+}
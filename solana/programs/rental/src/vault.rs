@@ -0,0 +1,121 @@
+use anchor_lang::prelude::*;
+
+use crate::math::checked_inc;
+use crate::token_interface;
+use crate::ErrorCode;
+
+// Escrow of the bookings, auctions & offers of nfts with an spl payment mint.
+// Escrowed tokens sit in a vault, a token account of the escrow pda for the
+// payment mint (its associated token account for clients), out of which the
+// escrow pda signs. Deposits pay the Token-2022 transfer fee on top, payouts
+// bear it
+pub struct Vault<'a, 'info> {
+    vault: &'a AccountInfo<'info>,
+    token_program: &'a AccountInfo<'info>,
+    mint: &'a AccountInfo<'info>,
+    decimals: u8,
+}
+
+impl<'a, 'info> Vault<'a, 'info> {
+    pub fn load(
+        vault: &'a AccountInfo<'info>,
+        token_program: &'a AccountInfo<'info>,
+        mint: &'a AccountInfo<'info>,
+        escrow: &Pubkey,
+        payment_mint: &Pubkey,
+    ) -> Result<Self> {
+        if mint.key() != *payment_mint {
+            return err!(ErrorCode::InvalidPaymentMint);
+        }
+        // The mint & the vault belong to the program doing the transfers
+        if !token_interface::is_token_program(token_program.key)
+            || mint.owner != token_program.key
+            || vault.owner != token_program.key
+        {
+            return err!(ErrorCode::InvalidTokenProgram);
+        }
+        let state = token_interface::token_account(vault)?;
+        if state.owner != *escrow || state.mint != *payment_mint {
+            return err!(ErrorCode::InvalidPaymentAccount);
+        }
+        let decimals = token_interface::mint(mint)?.decimals;
+        Ok(Vault {
+            vault,
+            token_program,
+            mint,
+            decimals,
+        })
+    }
+
+    // Vault accounts laid out as [vault, token program, payment mint]
+    pub fn from_accounts(
+        accounts: &'a [AccountInfo<'info>],
+        escrow: &Pubkey,
+        payment_mint: &Pubkey,
+    ) -> Result<Self> {
+        match accounts {
+            [vault, token_program, mint, ..] => {
+                Vault::load(vault, token_program, mint, escrow, payment_mint)
+            }
+            _ => err!(ErrorCode::MissingPaymentAccounts),
+        }
+    }
+
+    // Escrows `amount` out of `from`, a token account of the signing `owner`
+    pub fn deposit(
+        &self,
+        from: &AccountInfo<'info>,
+        owner: &AccountInfo<'info>,
+        amount: u64,
+    ) -> Result<()> {
+        self.check_token_account(from, &owner.key())?;
+        let fee = token_interface::transfer_fee(self.mint, amount)?;
+        token_interface::transfer_checked(
+            self.token_program,
+            from,
+            self.mint,
+            self.vault,
+            owner,
+            checked_inc(amount, fee)?,
+            self.decimals,
+            &[],
+        )
+    }
+
+    // Pays `amount` out to `to`, a token account of `owner`, the `escrow` pda
+    // signing with `seeds`
+    pub fn withdraw(
+        &self,
+        escrow: &AccountInfo<'info>,
+        seeds: &[&[u8]],
+        to: &AccountInfo<'info>,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+        self.check_token_account(to, owner)?;
+        token_interface::transfer_checked(
+            self.token_program,
+            self.vault,
+            self.mint,
+            to,
+            escrow,
+            amount,
+            self.decimals,
+            &[seeds],
+        )
+    }
+
+    fn check_token_account(&self, info: &AccountInfo, owner: &Pubkey) -> Result<()> {
+        if info.owner != self.token_program.key {
+            return err!(ErrorCode::InvalidTokenProgram);
+        }
+        let state = token_interface::token_account(info)?;
+        if state.owner != *owner || state.mint != self.mint.key() {
+            return err!(ErrorCode::InvalidPaymentAccount);
+        }
+        Ok(())
+    }
+}
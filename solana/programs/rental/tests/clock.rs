@@ -0,0 +1,661 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use rental::{Collection, Nft};
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account as SolanaAccount, AccountSharedData};
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_sdk::transport::TransportError;
+
+const DAY_S: i64 = 24 * 3600;
+const SYMBOL: &str = "Symb";
+const PRICE: u64 = 1_000_000;
+
+// Program test context with a warpable clock
+struct Harness {
+    context: ProgramTestContext,
+    owner: Keypair,
+    renter: Keypair,
+    mint: Pubkey,
+}
+
+impl Harness {
+    // Program without a config nor listings
+    async fn setup() -> Self {
+        let mut program = ProgramTest::new("rental", rental::id(), processor!(rental::entry));
+        let owner = Keypair::new();
+        let renter = Keypair::new();
+        for user in [&owner, &renter] {
+            program.add_account(
+                user.pubkey(),
+                SolanaAccount::new(100 * PRICE, 0, &system_program::ID),
+            );
+        }
+        let mut harness = Harness {
+            context: program.start_with_context().await,
+            owner,
+            renter,
+            mint: Pubkey::new_unique(),
+        };
+        harness.set_upgrade_authority();
+        harness
+    }
+
+    async fn start() -> Self {
+        let mut harness = Harness::setup().await;
+        harness.create_listing().await;
+        harness
+    }
+
+    async fn now(&mut self) -> i64 {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp
+    }
+
+    // Moves the clock sysvar `seconds` forward
+    async fn warp(&mut self, seconds: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn process(&mut self, ix: Instruction, signers: &[&Keypair]) -> Option<u32> {
+        // Retried transactions would otherwise share their signature
+        self.context.last_blockhash = self
+            .context
+            .banks_client
+            .get_new_latest_blockhash(&self.context.last_blockhash)
+            .await
+            .unwrap();
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            self.context.last_blockhash,
+        );
+        match self.context.banks_client.process_transaction(tx).await {
+            Ok(()) => None,
+            Err(TransportError::TransactionError(TransactionError::InstructionError(
+                _,
+                InstructionError::Custom(code),
+            ))) => Some(code),
+            Err(err) => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    async fn fetch<T: AccountDeserialize>(&mut self, pubkey: Pubkey) -> T {
+        let account = self
+            .context
+            .banks_client
+            .get_account(pubkey)
+            .await
+            .unwrap()
+            .unwrap();
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn collection_pda(&self) -> Pubkey {
+        let authority = self.context.payer.pubkey();
+        Pubkey::find_program_address(
+            &[b"collection", SYMBOL.as_bytes(), authority.as_ref()],
+            &rental::id(),
+        )
+        .0
+    }
+
+    fn config_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"config"], &rental::id()).0
+    }
+
+    fn program_data_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[rental::id().as_ref()], &bpf_loader_upgradeable::ID).0
+    }
+
+    fn nft_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"nft", self.mint.as_ref()], &rental::id()).0
+    }
+
+    fn record_pda(&self, index: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"rental_record", self.mint.as_ref(), &index.to_le_bytes()],
+            &rental::id(),
+        )
+        .0
+    }
+
+    fn auction_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"auction", self.mint.as_ref()], &rental::id()).0
+    }
+
+    fn cnft_pda(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"cnft", self.mint.as_ref()], &rental::id()).0
+    }
+
+    // Turns the listing into a compressed nft one, as left by create_cnft.
+    // Rentals don't touch the tree, which can't be set up here
+    fn list_as_cnft(&mut self) {
+        let mut data = hash(b"account:CompressedNft").to_bytes()[..8].to_vec();
+        data.extend_from_slice(self.mint.as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        // Data & creator hashes, nonce, index
+        data.extend_from_slice(&[0; 32 + 32 + 8 + 4]);
+        let account = SolanaAccount {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: rental::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context
+            .set_account(&self.cnft_pda(), &AccountSharedData::from(account));
+    }
+
+    // Builtin programs have no program data, the payer is made the upgrade
+    // authority of a deployed one
+    fn set_upgrade_authority(&mut self) {
+        // Serialized UpgradeableLoaderState::ProgramData: tag, slot, authority
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(self.context.payer.pubkey().as_ref());
+        let account = SolanaAccount {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context
+            .set_account(&self.program_data_pda(), &AccountSharedData::from(account));
+    }
+
+    async fn init_config(&mut self, admin: &Keypair) -> Option<u32> {
+        let admin = Keypair::from_bytes(&admin.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::InitConfig {
+                config: self.config_pda(),
+                admin: admin.pubkey(),
+                program_data: self.program_data_pda(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: rental::instruction::InitConfig {}.data(),
+        };
+        self.process(ix, &[&admin]).await
+    }
+
+    async fn create_listing(&mut self) {
+        let authority = self.context.payer.pubkey();
+        let payer = Keypair::from_bytes(&self.context.payer.to_bytes()).unwrap();
+        assert_eq!(self.init_config(&payer).await, None);
+
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::CreateCollection {
+                collection: self.collection_pda(),
+                authority,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: rental::instruction::CreateCollection {
+                _symbol: SYMBOL.to_string(),
+                name: "Collection".to_string(),
+                image_url: "".to_string(),
+                website_url: "".to_string(),
+                royalties_percent: 0,
+            }
+            .data(),
+        };
+        assert_eq!(self.process(ix, &[]).await, None);
+
+        let owner = Keypair::from_bytes(&self.owner.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::CreateNft {
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                owner: owner.pubkey(),
+                system_program: system_program::ID,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::CreateNft {
+                mint: self.mint,
+                collection: self.collection_pda(),
+                rental_max_days: 3,
+                rental_price: PRICE,
+                rental_enabled: true,
+                payment_mint: Pubkey::default(),
+            }
+            .data(),
+        };
+        assert_eq!(self.process(ix, &[&owner]).await, None);
+    }
+
+    async fn rent(&mut self, days: u32) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let renter = Keypair::from_bytes(&self.renter.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::RentNft {
+                collection: self.collection_pda(),
+                nft: self.nft_pda(),
+                bookings: Pubkey::find_program_address(
+                    &[b"bookings", self.mint.as_ref()],
+                    &rental::id(),
+                )
+                .0,
+                nft_access: system_program::ID,
+                collection_access: system_program::ID,
+                record: self.record_pda(nft.rental_count),
+                metadata: Pubkey::new_unique(),
+                token_metadata_program: system_program::ID,
+                owner: self.owner.pubkey(),
+                renter: renter.pubkey(),
+                system_program: system_program::ID,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::RentNft {
+                _mint: self.mint,
+                symbol: SYMBOL.to_string(),
+                autority: self.context.payer.pubkey(),
+                days,
+                nft_proof: vec![],
+                collection_proof: vec![],
+                pass_uri: None,
+            }
+            .data(),
+        };
+        self.process(ix, &[&renter]).await
+    }
+
+    // Rents the nft alone in a bundle, recording the rental without `init`
+    async fn rent_bundle(&mut self, days: u32) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let renter = Keypair::from_bytes(&self.renter.to_bytes()).unwrap();
+        let bookings =
+            Pubkey::find_program_address(&[b"bookings", self.mint.as_ref()], &rental::id()).0;
+        let mut accounts = rental::accounts::RentBundle {
+            owner: self.owner.pubkey(),
+            renter: renter.pubkey(),
+            token_metadata_program: system_program::ID,
+            system_program: system_program::ID,
+            config: self.config_pda(),
+        }
+        .to_account_metas(None);
+        accounts.extend([
+            AccountMeta::new(self.nft_pda(), false),
+            AccountMeta::new(self.collection_pda(), false),
+            AccountMeta::new_readonly(bookings, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new(self.record_pda(nft.rental_count), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+        ]);
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts,
+            data: rental::instruction::RentBundle {
+                count: 1,
+                days,
+                proofs: vec![vec![], vec![]],
+            }
+            .data(),
+        };
+        self.process(ix, &[&renter]).await
+    }
+
+    async fn release(&mut self) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::ReleaseRental {
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                record: self.record_pda(nft.rental_count.saturating_sub(1)),
+                metadata: Pubkey::new_unique(),
+                token_metadata_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: rental::instruction::ReleaseRental { _mint: self.mint }.data(),
+        };
+        self.process(ix, &[]).await
+    }
+
+    async fn delete(&mut self) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let owner = Keypair::from_bytes(&self.owner.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::DeleteNft {
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                bookings: Pubkey::find_program_address(
+                    &[b"bookings", self.mint.as_ref()],
+                    &rental::id(),
+                )
+                .0,
+                auction: self.auction_pda(),
+                cnft: self.cnft_pda(),
+                record: self.record_pda(nft.rental_count.saturating_sub(1)),
+                metadata: Pubkey::new_unique(),
+                token_metadata_program: system_program::ID,
+                owner: owner.pubkey(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::DeleteNft { _mint: self.mint }.data(),
+        };
+        self.process(ix, &[&owner]).await
+    }
+
+    async fn start_auction(&mut self, bidding_end: i64) -> Option<u32> {
+        let owner = Keypair::from_bytes(&self.owner.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::StartRentalAuction {
+                auction: self.auction_pda(),
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                owner: owner.pubkey(),
+                system_program: system_program::ID,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::StartRentalAuction {
+                mint: self.mint,
+                days: 1,
+                min_bid: PRICE,
+                bidding_end,
+            }
+            .data(),
+        };
+        self.process(ix, &[&owner]).await
+    }
+
+    async fn settle_auction(&mut self) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::SettleRentalAuction {
+                auction: self.auction_pda(),
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                bookings: Pubkey::find_program_address(
+                    &[b"bookings", self.mint.as_ref()],
+                    &rental::id(),
+                )
+                .0,
+                record: self.record_pda(nft.rental_count),
+                metadata: Pubkey::new_unique(),
+                token_metadata_program: system_program::ID,
+                owner: self.owner.pubkey(),
+                payer: self.context.payer.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: rental::instruction::SettleRentalAuction {}.data(),
+        };
+        self.process(ix, &[]).await
+    }
+
+    async fn place_bid(&mut self, amount: u64) -> Option<u32> {
+        let renter = Keypair::from_bytes(&self.renter.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::PlaceBid {
+                auction: self.auction_pda(),
+                nft: self.nft_pda(),
+                collection: self.collection_pda(),
+                nft_access: system_program::ID,
+                collection_access: system_program::ID,
+                bidder: renter.pubkey(),
+                system_program: system_program::ID,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::PlaceBid {
+                amount,
+                nft_proof: vec![],
+                collection_proof: vec![],
+            }
+            .data(),
+        };
+        self.process(ix, &[&renter]).await
+    }
+
+    async fn cancel_auction(&mut self) -> Option<u32> {
+        let owner = Keypair::from_bytes(&self.owner.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::CancelRentalAuction {
+                auction: self.auction_pda(),
+                nft: self.nft_pda(),
+                owner: owner.pubkey(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::CancelRentalAuction {}.data(),
+        };
+        self.process(ix, &[&owner]).await
+    }
+
+    async fn rent_cnft(&mut self, days: u32) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let renter = Keypair::from_bytes(&self.renter.to_bytes()).unwrap();
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::RentCnft {
+                nft: self.nft_pda(),
+                cnft: self.cnft_pda(),
+                collection: self.collection_pda(),
+                bookings: Pubkey::find_program_address(
+                    &[b"bookings", self.mint.as_ref()],
+                    &rental::id(),
+                )
+                .0,
+                nft_access: system_program::ID,
+                collection_access: system_program::ID,
+                record: self.record_pda(nft.rental_count),
+                owner: self.owner.pubkey(),
+                renter: renter.pubkey(),
+                system_program: system_program::ID,
+                config: self.config_pda(),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::RentCnft {
+                asset_id: self.mint,
+                days,
+                nft_proof: vec![],
+                collection_proof: vec![],
+            }
+            .data(),
+        };
+        self.process(ix, &[&renter]).await
+    }
+
+    async fn release_cnft(&mut self) -> Option<u32> {
+        let nft: Nft = self.fetch(self.nft_pda()).await;
+        let ix = Instruction {
+            program_id: rental::id(),
+            accounts: rental::accounts::ReleaseCnft {
+                nft: self.nft_pda(),
+                cnft: self.cnft_pda(),
+                collection: self.collection_pda(),
+                record: self.record_pda(nft.rental_count.saturating_sub(1)),
+            }
+            .to_account_metas(None),
+            data: rental::instruction::ReleaseCnft {
+                asset_id: self.mint,
+            }
+            .data(),
+        };
+        self.process(ix, &[]).await
+    }
+}
+
+fn code(error: rental::ErrorCode) -> Option<u32> {
+    Some(error.into())
+}
+
+#[tokio::test]
+async fn config_is_created_by_upgrade_authority() {
+    let mut harness = Harness::setup().await;
+    let owner = Keypair::from_bytes(&harness.owner.to_bytes()).unwrap();
+    assert_eq!(
+        harness.init_config(&owner).await,
+        code(rental::ErrorCode::NotUpgradeAuthority)
+    );
+
+    let payer = Keypair::from_bytes(&harness.context.payer.to_bytes()).unwrap();
+    assert_eq!(harness.init_config(&payer).await, None);
+}
+
+#[tokio::test]
+async fn rental_expires_after_its_term() {
+    let mut harness = Harness::start().await;
+    assert_eq!(harness.rent(2).await, None);
+    let rented_at = harness.now().await;
+
+    assert_eq!(harness.rent(1).await, code(rental::ErrorCode::NftRented));
+    harness.warp(2 * DAY_S - 60).await;
+    assert_eq!(harness.rent(3).await, code(rental::ErrorCode::NftRented));
+
+    // Rentable again once the term is over
+    harness.warp(120).await;
+    assert_eq!(harness.rent(1).await, None);
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.rental_count, 2);
+    assert_eq!(nft.rented_until, rented_at + 2 * DAY_S + 60 + DAY_S);
+}
+
+#[tokio::test]
+async fn bundle_rental_records_prefunded_address() {
+    let mut harness = Harness::start().await;
+    // Lamports sent to the next record address ahead of the rental
+    let record = harness.record_pda(0);
+    let account = SolanaAccount::new(1, 0, &system_program::ID);
+    harness
+        .context
+        .set_account(&record, &AccountSharedData::from(account));
+
+    assert_eq!(harness.rent_bundle(1).await, None);
+    let account = harness
+        .context
+        .banks_client
+        .get_account(record)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, rental::id());
+    assert_eq!(
+        account.lamports,
+        Rent::default().minimum_balance(account.data.len())
+    );
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.rental_count, 1);
+    assert_eq!(nft.renter, harness.renter.pubkey());
+}
+
+#[tokio::test]
+async fn rental_is_released_once_expired() {
+    let mut harness = Harness::start().await;
+    assert_eq!(harness.rent(1).await, None);
+    assert_eq!(harness.release().await, code(rental::ErrorCode::NftRented));
+
+    harness.warp(DAY_S).await;
+    assert_eq!(harness.release().await, None);
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.renter, Pubkey::default());
+    let collection: Collection = harness.fetch(harness.collection_pda()).await;
+    assert_eq!(collection.active_rentals, 0);
+}
+
+#[tokio::test]
+async fn cnft_rental_is_released_once_expired() {
+    let mut harness = Harness::start().await;
+    harness.list_as_cnft();
+    assert_eq!(harness.rent_cnft(1).await, None);
+    assert_eq!(
+        harness.release_cnft().await,
+        code(rental::ErrorCode::NftRented)
+    );
+
+    harness.warp(DAY_S).await;
+    assert_eq!(harness.release_cnft().await, None);
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.renter, Pubkey::default());
+    let collection: Collection = harness.fetch(harness.collection_pda()).await;
+    assert_eq!(collection.active_rentals, 0);
+    assert_eq!(harness.rent_cnft(2).await, None);
+}
+
+#[tokio::test]
+async fn auction_without_bids_is_cancelled() {
+    let mut harness = Harness::start().await;
+    let bidding_end = harness.now().await + 60;
+    assert_eq!(harness.start_auction(bidding_end).await, None);
+    harness.warp(120).await;
+
+    // Settling would leave an empty record at the next rental index
+    assert_eq!(
+        harness.settle_auction().await,
+        code(rental::ErrorCode::NoBids)
+    );
+    assert_eq!(harness.cancel_auction().await, None);
+    assert_eq!(harness.rent(1).await, None);
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.rental_count, 1);
+}
+
+#[tokio::test]
+async fn auction_settles_once_rental_is_over() {
+    let mut harness = Harness::start().await;
+    assert_eq!(harness.rent(1).await, None);
+    let bidding_end = harness.now().await + 60;
+    assert_eq!(harness.start_auction(bidding_end).await, None);
+    assert_eq!(harness.place_bid(2 * PRICE).await, None);
+
+    // The won window starts at settlement, after the ongoing rental
+    harness.warp(DAY_S).await;
+    assert_eq!(harness.settle_auction().await, None);
+    let settled_at = harness.now().await;
+    let nft: Nft = harness.fetch(harness.nft_pda()).await;
+    assert_eq!(nft.renter, harness.renter.pubkey());
+    assert_eq!(nft.rented_until, settled_at + DAY_S);
+    assert_eq!(nft.rental_count, 2);
+    let collection: Collection = harness.fetch(harness.collection_pda()).await;
+    assert_eq!(collection.active_rentals, 1);
+}
+
+#[tokio::test]
+async fn delisting_requires_closed_auction() {
+    let mut harness = Harness::start().await;
+    let bidding_end = harness.now().await + 60;
+    assert_eq!(harness.start_auction(bidding_end).await, None);
+    assert_eq!(harness.delete().await, code(rental::ErrorCode::AuctionOpen));
+
+    // Cancelling refunds the bids, the nft can then be delisted
+    assert_eq!(harness.cancel_auction().await, None);
+    assert_eq!(harness.delete().await, None);
+    let collection: Collection = harness.fetch(harness.collection_pda()).await;
+    assert_eq!(collection.listing_count, 0);
+}
+
+#[tokio::test]
+async fn cnft_listing_is_not_deleted_as_nft() {
+    let mut harness = Harness::start().await;
+    harness.list_as_cnft();
+    // Only delete_cnft returns the leaf out of the program custody
+    assert_eq!(
+        harness.delete().await,
+        code(rental::ErrorCode::CompressedListing)
+    );
+    let collection: Collection = harness.fetch(harness.collection_pda()).await;
+    assert_eq!(collection.listing_count, 1);
+}
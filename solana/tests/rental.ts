@@ -1,10 +1,38 @@
 import { Program } from "@project-serum/anchor";
 import { Rental } from "../target/types/rental";
-// import { Metadata } from "@metaplex-foundation/mpl-token-metadata";
+import { Metadata } from "@metaplex-foundation/mpl-token-metadata";
 import * as anchor from "@project-serum/anchor";
 import { LAMPORTS_PER_SOL } from "@solana/web3.js";
+import {
+  createAssociatedTokenAccountInstruction,
+  createMint,
+  createTransferInstruction,
+  getAccount,
+  getAssociatedTokenAddress,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+} from "@solana/spl-token";
 const { SystemProgram } = anchor.web3;
 import * as assert from "assert";
+import {
+  ConcurrentMerkleTreeAccount,
+  createAllocTreeIx,
+  MerkleTree,
+  SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+  SPL_NOOP_PROGRAM_ID,
+  ValidDepthSizePair,
+} from "@solana/spl-account-compression";
+import {
+  computeCompressedNFTHash,
+  computeCreatorHash,
+  computeDataHash,
+  createCreateTreeInstruction,
+  createMintV1Instruction,
+  MetadataArgs,
+  PROGRAM_ID as BUBBLEGUM_PROGRAM_ID,
+  TokenProgramVersion,
+  TokenStandard,
+} from "@metaplex-foundation/mpl-bubblegum";
 
 import web3 = anchor.web3;
 
@@ -15,7 +43,11 @@ import {
   CollectionApp,
   NFT,
 } from "../app/api";
-import { MintData, getNftAPI } from "../app/nftApi";
+import {
+  MintData,
+  getCreatorAuthorityPubkey,
+  getNftAPI,
+} from "../app/nftApi";
 
 // Configure the client to use the local cluster.
 anchor.setProvider(anchor.AnchorProvider.env());
@@ -29,10 +61,24 @@ const {
   airdrop,
   getCollectionPda,
   getNFTPda,
+  getCNFTCustodyPda,
+  getMetadataPda,
+  // Config
+  initConfig,
+  setPaused,
   // Collection
   fetchCollection,
   createCollection,
   updateCollection,
+  updateCollectionPolicy,
+  setRentedMetadata,
+  closeCollection,
+  // Collection mints
+  createCollectionMint,
+  mintAndList,
+  fetchMintGate,
+  initMintGate,
+  updateMintGate,
   // Collection apps
   fetchCollectionApp,
   fetchCollectionApps,
@@ -44,13 +90,67 @@ const {
   fetchNFTs,
   createNFT,
   updateNFT,
+  deleteNFT,
   rentNFT,
+  rentBundle,
+  // Delegation
+  delegateNFT,
+  endRental,
+  // Compressed nfts
+  fetchCNFT,
+  createCNFT,
+  rentCNFT,
+  releaseCNFT,
+  deleteCNFT,
+  // Rental passes
+  fetchRentalPass,
+  burnExpiredPass,
+  // Rental records
+  fetchRentalRecord,
+  closeRentalRecord,
+  // Bookings
+  fetchBookings,
+  initBookings,
+  bookNFT,
+  cancelBooking,
+  // Auctions
+  startRentalAuction,
+  placeBid,
+  settleRentalAuction,
+  cancelRentalAuction,
+  // Offers
+  fetchOffers,
+  createRentalOffer,
+  acceptRentalOffer,
+  cancelRentalOffer,
+  // Access
+  setNFTAccess,
+  addNFTAccessEntry,
 } = getAPI(program);
 
 const { mint } = getNftAPI(program);
 
 const mintData = {} as MintData;
 
+// Checks the wallet can't transfer its `mint` nft to `to`
+async function assertFrozen(mint: web3.PublicKey, to: web3.PublicKey) {
+  const wallet = provider.wallet.publicKey;
+  const source = await getAssociatedTokenAddress(mint, wallet);
+  const destination = await getAssociatedTokenAddress(mint, to);
+  try {
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(
+        createAssociatedTokenAccountInstruction(wallet, destination, to, mint),
+        createTransferInstruction(source, destination, wallet, 1)
+      )
+    );
+    assert.fail("Frozen nft transferred");
+  } catch (e) {
+    // Token program AccountFrozen error
+    assert.ok(e.logs.some((log) => log.includes("Account is frozen")));
+  }
+}
+
 function stripBn(obj) {
   Object.keys(obj).forEach((key) => {
     // console.log('key', key, 'instance', obj[key] instanceof anchor.BN);
@@ -60,7 +160,7 @@ function stripBn(obj) {
   return obj;
 }
 
-const MINT_NFTS = true;
+const MINT_NFTS = false;
 
 describe("rental", () => {
   // Collection key
@@ -72,6 +172,7 @@ describe("rental", () => {
   // Generate users
   const owner = web3.Keypair.generate();
   const renter = web3.Keypair.generate();
+  const bidder = web3.Keypair.generate();
 
   if (MINT_NFTS) {
     it("mints_nfts", async () => {
@@ -88,6 +189,8 @@ describe("rental", () => {
   it("setup", async () => {
     await airdrop(owner.publicKey, 10 * LAMPORTS_PER_SOL);
     await airdrop(renter.publicKey, 10 * LAMPORTS_PER_SOL);
+    await airdrop(bidder.publicKey, 10 * LAMPORTS_PER_SOL);
+    await initConfig(provider.wallet.publicKey);
   });
 
   it("create collection", async () => {
@@ -99,10 +202,22 @@ describe("rental", () => {
       royaltiesPercent: 0,
       // Assertion fields
       appCount: 0,
+      listingCount: 0,
+      totalRentals: new anchor.BN(0),
+      volume: new anchor.BN(0),
+      activeRentals: 0,
+      collectionMint: web3.PublicKey.default,
+      authority: key.authority,
+      rentedMetadata: false,
     } as Collection;
     await createCollection(key, collection);
-    const collectionRetrieved = await fetchCollection(key);
-    assert.deepEqual(collection, collectionRetrieved);
+    const { policy, access, ...collectionRetrieved } = await fetchCollection(
+      key
+    );
+    assert.deepEqual(stripBn(collection), stripBn(collectionRetrieved));
+    assert.ok(policy.rentalEnabled);
+    assert.equal(policy.paymentMints.length, 0);
+    assert.deepEqual(access, { open: {} });
   });
 
   it("update collection", async () => {
@@ -116,6 +231,22 @@ describe("rental", () => {
     assert.deepEqual(collection, collectionRetrieved);
   });
 
+  it("sets mint gate", async () => {
+    const gate = {
+      price: new anchor.BN(LAMPORTS_PER_SOL),
+      paymentMint: web3.PublicKey.default,
+      access: { open: {} },
+      maxSupply: 100,
+    };
+    await initMintGate(key, gate);
+    await updateMintGate(key, { ...gate, maxSupply: 50 });
+    const mintGate = await fetchMintGate(key);
+    assert.equal(mintGate.price.toNumber(), LAMPORTS_PER_SOL);
+    assert.equal(mintGate.maxSupply, 50);
+    assert.equal(mintGate.minted, 0);
+    assert.ok(mintGate.access.open);
+  });
+
   it("create collection app", async () => {
     const collectionPda = await getCollectionPda(key);
     const app = {
@@ -161,9 +292,14 @@ describe("rental", () => {
       rentalEnabled: false,
       rentalPrice: new anchor.BN(LAMPORTS_PER_SOL * 1),
       rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
       // Assertion fields
+      access: { open: {} },
+      tokenAccount: new web3.PublicKey(0),
       collection: collectionPda.pda,
       rentalCount: new anchor.BN(0),
+      totalEarned: new anchor.BN(0),
+      totalDaysRented: new anchor.BN(0),
       owner: owner.publicKey,
       renter: new web3.PublicKey(0),
       mint: mint.publicKey,
@@ -190,6 +326,23 @@ describe("rental", () => {
     assert.deepEqual(stripBn(nft), stripBn(fetched));
   });
 
+  it("enforces collection policy", async () => {
+    const collection = await fetchCollection(key);
+    const nfts = await fetchNFTs(key);
+    const nft = nfts[0].account;
+    await updateCollectionPolicy(key, {
+      ...collection.policy,
+      maxPrice: new anchor.BN(nft.rentalPrice.toNumber() - 1),
+    });
+    try {
+      await updateNFT(nft.mint, nft, owner.publicKey, [owner]);
+      assert.fail("Update above collection max price succeeded");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "RentalPriceOutOfBounds");
+    }
+    await updateCollectionPolicy(key, collection.policy);
+  });
+
   it("rents nft", async () => {
     const nfts = await fetchNFTs(key);
     assert.equal(nfts.length, 1);
@@ -214,6 +367,35 @@ describe("rental", () => {
     // console.log('until', fetched.rentedUntil.toNumber(), 'now', now);
     const deltaTime = fetched.rentedUntil.toNumber() - now - days * DAY_S;
     assert.ok(Math.abs(deltaTime) < 30);
+
+    // Rental stats
+    assert.equal(fetched.rentalCount.toNumber(), 1);
+    assert.equal(fetched.totalDaysRented.toNumber(), days);
+    assert.equal(
+      fetched.totalEarned.toNumber(),
+      nft.rentalPrice.toNumber() * days
+    );
+    const collection = await fetchCollection(key);
+    assert.equal(collection.totalRentals.toNumber(), 1);
+    assert.equal(collection.activeRentals, 1);
+    assert.equal(
+      collection.volume.toNumber(),
+      nft.rentalPrice.toNumber() * days
+    );
+
+    // Rental record
+    const record = await fetchRentalRecord(nft.mint, 0);
+    assert.equal(record.renter.toBase58(), renter.publicKey.toBase58());
+    assert.equal(record.payer.toBase58(), renter.publicKey.toBase58());
+    assert.equal(record.end.toNumber(), fetched.rentedUntil.toNumber());
+    assert.equal(record.amount.toNumber(), nft.rentalPrice.toNumber() * days);
+    assert.deepEqual(record.reason, { ongoing: {} });
+    try {
+      await closeRentalRecord(nft.mint, 0, renter.publicKey, [renter]);
+      assert.fail("Rental record closed before retention");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "RecordRetained");
+    }
   });
 
   it("can't double rent", async () => {
@@ -225,6 +407,671 @@ describe("rental", () => {
       assert.fail("Double rental succeeded");
     } catch (e) {
       assert.equal(e.error.errorCode.code, "NftRented");
+      assert.equal(e.error.errorCode.number, 6101);
+    }
+  });
+
+  it("pauses program", async () => {
+    const nfts = await fetchNFTs(key);
+    const nft = nfts[0].account;
+    await setPaused(true, provider.wallet.publicKey);
+    try {
+      await rentNFT(key, nft.mint, 1, nft.owner, renter.publicKey, [renter]);
+      assert.fail("Rental succeeded while paused");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "ProgramPaused");
+      assert.equal(e.error.errorCode.number, 7400);
+    } finally {
+      await setPaused(false, provider.wallet.publicKey);
+    }
+  });
+
+  it("books nft", async () => {
+    const nfts = await fetchNFTs(key);
+    const nft = nfts[0].account;
+    await initBookings(nft.mint, DAY_S, 50, owner.publicKey, [owner]);
+    // Book a slot after the ongoing rental
+    const start = nft.rentedUntil.toNumber() + DAY_S;
+    await bookNFT(nft, start, 1, renter.publicKey, [renter]);
+    let bookings = await fetchBookings(nft.mint);
+    assert.equal(bookings.reservations.length, 1);
+    assert.equal(bookings.reservations[0].start.toNumber(), start);
+    // Overlapping slots are rejected
+    try {
+      await bookNFT(nft, start, 1, renter.publicKey, [renter]);
+      assert.fail("Overlapping booking succeeded");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftBooked");
+    }
+    // The payment mint is kept while reservations are pending
+    try {
+      await updateNFT(
+        nft.mint,
+        { ...nft, paymentMint: web3.Keypair.generate().publicKey },
+        owner.publicKey,
+        [owner]
+      );
+      assert.fail("Payment mint changed with pending reservations");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftBooked");
+    }
+    // Cancelling with enough notice is fully refunded
+    const balanceBefore = await connection.getBalance(renter.publicKey);
+    await cancelBooking(nft, start, renter.publicKey, [renter]);
+    const balanceAfter = await connection.getBalance(renter.publicKey);
+    bookings = await fetchBookings(nft.mint);
+    assert.equal(bookings.reservations.length, 0);
+    assert.equal(balanceAfter - balanceBefore, nft.rentalPrice.toNumber());
+  });
+
+  it("auctions next rental window", async () => {
+    const nfts = await fetchNFTs(key);
+    const nft = nfts[0].account;
+    const biddingEnd = Math.floor(Date.now() / 1e3) + 5;
+    await startRentalAuction(nft, 1, new anchor.BN(1000), biddingEnd, [owner]);
+    await placeBid(nft.mint, new anchor.BN(2000), renter.publicKey, [renter]);
+    await placeBid(nft.mint, new anchor.BN(3000), bidder.publicKey, [bidder]);
+    const balanceBefore = await connection.getBalance(renter.publicKey);
+    await new Promise((resolve) => setTimeout(resolve, 8000));
+    // The ongoing rental keeps its renter until its term
+    try {
+      await settleRentalAuction(nft);
+      assert.fail("Auction settled during a rental");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftRented");
+    }
+    const fetched = await fetchNFT(nft.mint);
+    assert.equal(fetched.renter.toBase58(), renter.publicKey.toBase58());
+    // Cancelling refunds every bidder
+    await cancelRentalAuction(nft, [owner]);
+    const balanceAfter = await connection.getBalance(renter.publicKey);
+    assert.equal(balanceAfter - balanceBefore, 2000);
+  });
+
+  it("creates and cancels rental offer", async () => {
+    const nfts = await fetchNFTs(key);
+    const nft = nfts[0].account;
+    const expiresAt = Math.floor(Date.now() / 1e3) + DAY_S;
+    const balanceBefore = await connection.getBalance(renter.publicKey);
+    await createRentalOffer(
+      nft.mint,
+      new anchor.BN(LAMPORTS_PER_SOL / 2),
+      2,
+      expiresAt,
+      renter.publicKey,
+      [renter]
+    );
+    const offers = await fetchOffers(nft.mint);
+    assert.equal(offers.length, 1);
+    assert.equal(offers[0].account.days, 2);
+    // Cancelling refunds the escrow and the account rent
+    await cancelRentalOffer(nft.mint, renter.publicKey, [renter]);
+    const balanceAfter = await connection.getBalance(renter.publicKey);
+    assert.equal(balanceAfter, balanceBefore);
+  });
+
+  it("escrows spl payments in token vaults", async () => {
+    const nfts = await fetchNFTs(key);
+    const wallet = provider.wallet as anchor.Wallet;
+    const paymentMint = await createMint(
+      connection,
+      wallet.payer,
+      wallet.publicKey,
+      null,
+      0
+    );
+    const funds = 10 * LAMPORTS_PER_SOL;
+    for (const user of [owner, renter, bidder]) {
+      const account = await getOrCreateAssociatedTokenAccount(
+        connection,
+        wallet.payer,
+        paymentMint,
+        user.publicKey
+      );
+      await mintTo(
+        connection,
+        wallet.payer,
+        paymentMint,
+        account.address,
+        wallet.publicKey,
+        funds
+      );
+    }
+    const balance = async (user: web3.Keypair) => {
+      const address = await getAssociatedTokenAddress(
+        paymentMint,
+        user.publicKey
+      );
+      return Number((await getAccount(connection, address)).amount);
+    };
+    const listed = nfts[0].account;
+    await updateNFT(listed.mint, { ...listed, paymentMint }, owner.publicKey, [
+      owner,
+    ]);
+    const nft = await fetchNFT(listed.mint);
+    // Bookings are escrowed in the bookings vault
+    const start = nft.rentedUntil.toNumber() + DAY_S;
+    await bookNFT(nft, start, 1, renter.publicKey, [renter]);
+    assert.equal(await balance(renter), funds - nft.rentalPrice.toNumber());
+    await cancelBooking(nft, start, renter.publicKey, [renter]);
+    assert.equal(await balance(renter), funds);
+    // Raised bids only escrow the difference in the auction vault
+    const expiresAt = Math.floor(Date.now() / 1e3) + DAY_S;
+    await startRentalAuction(nft, 1, new anchor.BN(1000), expiresAt, [owner]);
+    await placeBid(nft.mint, new anchor.BN(2000), bidder.publicKey, [bidder]);
+    await placeBid(nft.mint, new anchor.BN(3000), bidder.publicKey, [bidder]);
+    assert.equal(await balance(bidder), funds - 3000);
+    await cancelRentalAuction(nft, [owner]);
+    assert.equal(await balance(bidder), funds);
+    // Offers are escrowed in full in the offer vault
+    await createRentalOffer(
+      nft.mint,
+      new anchor.BN(1000),
+      2,
+      expiresAt,
+      renter.publicKey,
+      [renter]
+    );
+    assert.equal(await balance(renter), funds - 2000);
+    await cancelRentalOffer(nft.mint, renter.publicKey, [renter]);
+    assert.equal(await balance(renter), funds);
+    await updateNFT(listed.mint, listed, owner.publicKey, [owner]);
+  });
+
+  it("rents nft bundle", async () => {
+    const collectionPda = await getCollectionPda(key);
+    const bundle: NFT[] = [];
+    for (let k = 0; k < 2; k++) {
+      const mint = web3.Keypair.generate();
+      const nft = {
+        rentalEnabled: true,
+        rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+        rentalMaxDays: 3,
+        paymentMint: new web3.PublicKey(0),
+      } as NFT;
+      await createNFT(mint.publicKey, collectionPda.pda, nft, owner.publicKey, [
+        owner,
+      ]);
+      bundle.push(await fetchNFT(mint.publicKey));
+    }
+    const balanceBefore = await connection.getBalance(renter.publicKey);
+    await rentBundle(bundle, 2, renter.publicKey, [renter]);
+    // All nfts are rented until the same time, for the summed price
+    const fetched = await Promise.all(bundle.map((nft) => fetchNFT(nft.mint)));
+    fetched.forEach((nft) => {
+      assert.equal(nft.renter.toBase58(), renter.publicKey.toBase58());
+      assert.equal(
+        nft.rentedUntil.toNumber(),
+        fetched[0].rentedUntil.toNumber()
+      );
+    });
+    const balanceAfter = await connection.getBalance(renter.publicKey);
+    assert.equal(balanceBefore - balanceAfter, (2 * 2 * LAMPORTS_PER_SOL) / 10);
+    // A bundle fails as a whole
+    try {
+      await rentBundle(bundle, 1, renter.publicKey, [renter]);
+      assert.fail("Bundle double rental succeeded");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftRented");
+    }
+  });
+
+  it("enforces nft allowlist", async () => {
+    const mint = web3.Keypair.generate();
+    const collectionPda = await getCollectionPda(key);
+    const nft = {
+      rentalEnabled: true,
+      rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
+    } as NFT;
+    await createNFT(mint.publicKey, collectionPda.pda, nft, owner.publicKey, [
+      owner,
+    ]);
+    await setNFTAccess(mint.publicKey, { allowlist: {} }, owner.publicKey, [
+      owner,
+    ]);
+    try {
+      await rentNFT(key, mint.publicKey, 1, owner.publicKey, renter.publicKey, [
+        renter,
+      ]);
+      assert.fail("Rental outside of allowlist succeeded");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "AccessDenied");
+    }
+    await addNFTAccessEntry(
+      mint.publicKey,
+      renter.publicKey,
+      owner.publicKey,
+      [owner]
+    );
+    await rentNFT(key, mint.publicKey, 1, owner.publicKey, renter.publicKey, [
+      renter,
+    ]);
+    const fetched = await fetchNFT(mint.publicKey);
+    assert.equal(fetched.renter.toBase58(), renter.publicKey.toBase58());
+  });
+
+  it("rejects denied bidders and offers", async () => {
+    const mint = web3.Keypair.generate();
+    const collectionPda = await getCollectionPda(key);
+    const nft = {
+      rentalEnabled: true,
+      rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
+    } as NFT;
+    await createNFT(mint.publicKey, collectionPda.pda, nft, owner.publicKey, [
+      owner,
+    ]);
+    await setNFTAccess(mint.publicKey, { denylist: {} }, owner.publicKey, [
+      owner,
+    ]);
+    await addNFTAccessEntry(
+      mint.publicKey,
+      bidder.publicKey,
+      owner.publicKey,
+      [owner]
+    );
+    const fetched = await fetchNFT(mint.publicKey);
+    const biddingEnd = Math.floor(Date.now() / 1e3) + 60;
+    await startRentalAuction(fetched, 1, new anchor.BN(1000), biddingEnd, [
+      owner,
+    ]);
+    try {
+      await placeBid(mint.publicKey, new anchor.BN(2000), bidder.publicKey, [
+        bidder,
+      ]);
+      assert.fail("Denied bidder placed a bid");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "AccessDenied");
+    }
+    try {
+      await createRentalOffer(
+        mint.publicKey,
+        new anchor.BN(LAMPORTS_PER_SOL / 2),
+        1,
+        biddingEnd,
+        bidder.publicKey,
+        [bidder]
+      );
+      assert.fail("Denied renter made an offer");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "AccessDenied");
+    }
+    // Renters outside of the denylist can still bid
+    await placeBid(mint.publicKey, new anchor.BN(2000), renter.publicKey, [
+      renter,
+    ]);
+    await cancelRentalAuction(fetched, [owner]);
+  });
+
+  it("mints a rental pass per rental", async () => {
+    const mint = web3.Keypair.generate();
+    const collectionPda = await getCollectionPda(key);
+    const nft = {
+      rentalEnabled: true,
+      rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
+    } as NFT;
+    await createNFT(mint.publicKey, collectionPda.pda, nft, owner.publicKey, [
+      owner,
+    ]);
+    const passMint = await rentNFT(
+      key,
+      mint.publicKey,
+      1,
+      owner.publicKey,
+      renter.publicKey,
+      [renter],
+      [],
+      {},
+      "https://some_pass.json"
+    );
+    const fetched = await fetchNFT(mint.publicKey);
+    const pass = await fetchRentalPass(passMint);
+    assert.equal(pass.mint.toBase58(), mint.publicKey.toBase58());
+    assert.equal(pass.renter.toBase58(), renter.publicKey.toBase58());
+    assert.equal(pass.expiresAt.toNumber(), fetched.rentedUntil.toNumber());
+    const record = await fetchRentalRecord(mint.publicKey, 0);
+    assert.equal(record.passMint.toBase58(), passMint.toBase58());
+    // The pass is frozen in the renter wallet
+    const passAccount = await getAccount(
+      connection,
+      await getAssociatedTokenAddress(passMint, renter.publicKey)
+    );
+    assert.equal(passAccount.amount, BigInt(1));
+    assert.ok(passAccount.isFrozen);
+    // Passes are burnt once the rental expired only
+    try {
+      await burnExpiredPass(passMint);
+      assert.fail("Rental pass burnt before expiry");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "PassNotExpired");
+    }
+  });
+
+  it("freezes delegated nft while rented", async () => {
+    const collectionPda = await getCollectionPda(key);
+    const wallet = provider.wallet.publicKey;
+    const { mint: nftMint } = await mint(
+      "https://www.arweave.net/bxIAEavpOJeRyOKJFbMFjCZ4To6PNVefyj5epo40OCA/",
+      "CryptoPet #2641",
+      "CP"
+    );
+    const nft = {
+      rentalEnabled: true,
+      rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
+    } as NFT;
+    await createNFT(nftMint, collectionPda.pda, nft, wallet);
+    await delegateNFT(nftMint, wallet);
+    // A delegated nft is delisted only once the delegation ended
+    try {
+      await deleteNFT(nftMint, collectionPda.pda, wallet);
+      assert.fail("Delegated nft delisted");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftDelegated");
+    }
+    await rentNFT(key, nftMint, 1, wallet, renter.publicKey, [renter]);
+    // The owner can't move the nft out of its wallet during the rental
+    await assertFrozen(nftMint, owner.publicKey);
+    // Nor end the rental before its term
+    try {
+      await endRental(nftMint, wallet);
+      assert.fail("Rental ended before its term");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftRented");
+    }
+  });
+
+  it("rents delegated nft bundle", async () => {
+    const collectionPda = await getCollectionPda(key);
+    const wallet = provider.wallet.publicKey;
+    const bundle: NFT[] = [];
+    for (let k = 0; k < 2; k++) {
+      const { mint: nftMint } = await mint(
+        "https://www.arweave.net/bxIAEavpOJeRyOKJFbMFjCZ4To6PNVefyj5epo40OCA/",
+        `CryptoPet #${2650 + k}`,
+        "CP"
+      );
+      const nft = {
+        rentalEnabled: true,
+        rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+        rentalMaxDays: 3,
+        paymentMint: new web3.PublicKey(0),
+      } as NFT;
+      await createNFT(nftMint, collectionPda.pda, nft, wallet);
+      // Only the first nft is delegated
+      if (k === 0) await delegateNFT(nftMint, wallet);
+      bundle.push(await fetchNFT(nftMint));
+    }
+    await rentBundle(bundle, 1, renter.publicKey, [renter]);
+    const fetched = await Promise.all(bundle.map((nft) => fetchNFT(nft.mint)));
+    fetched.forEach((nft) =>
+      assert.equal(nft.renter.toBase58(), renter.publicKey.toBase58())
+    );
+    await assertFrozen(bundle[0].mint, owner.publicKey);
+  });
+
+  it("rents compressed nft", async () => {
+    // Local concurrent merkle tree, mirrored off-chain for the leaf proofs
+    const depthSize: ValidDepthSizePair = { maxDepth: 3, maxBufferSize: 8 };
+    const merkleTree = web3.Keypair.generate();
+    const [treeAuthority] = await web3.PublicKey.findProgramAddress(
+      [merkleTree.publicKey.toBuffer()],
+      BUBBLEGUM_PROGRAM_ID
+    );
+    const payer = provider.wallet.publicKey;
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(
+        await createAllocTreeIx(
+          connection,
+          merkleTree.publicKey,
+          payer,
+          depthSize,
+          0
+        ),
+        createCreateTreeInstruction(
+          {
+            treeAuthority,
+            merkleTree: merkleTree.publicKey,
+            payer,
+            treeCreator: payer,
+            logWrapper: SPL_NOOP_PROGRAM_ID,
+            compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+          },
+          { ...depthSize, public: false }
+        )
+      ),
+      [merkleTree]
+    );
+
+    // Mint the first leaf to the owner
+    const metadata: MetadataArgs = {
+      name: "Compressed #1",
+      symbol: key.symbol,
+      uri: "https://some_uri.json",
+      sellerFeeBasisPoints: 0,
+      primarySaleHappened: false,
+      isMutable: false,
+      editionNonce: null,
+      tokenStandard: TokenStandard.NonFungible,
+      collection: null,
+      uses: null,
+      tokenProgramVersion: TokenProgramVersion.Original,
+      creators: [],
+    };
+    await provider.sendAndConfirm(
+      new web3.Transaction().add(
+        createMintV1Instruction(
+          {
+            merkleTree: merkleTree.publicKey,
+            treeAuthority,
+            treeDelegate: payer,
+            payer,
+            leafOwner: owner.publicKey,
+            leafDelegate: owner.publicKey,
+            logWrapper: SPL_NOOP_PROGRAM_ID,
+            compressionProgram: SPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+          },
+          { message: metadata }
+        )
+      )
+    );
+    const nonce = new anchor.BN(0);
+    const [assetId] = await web3.PublicKey.findProgramAddress(
+      [
+        Buffer.from("asset"),
+        merkleTree.publicKey.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      BUBBLEGUM_PROGRAM_ID
+    );
+    // Root & proof of the leaf once held by `leafOwner`
+    const leafProof = (
+      leafOwner: web3.PublicKey,
+      leafDelegate: web3.PublicKey
+    ) => {
+      const leaf = computeCompressedNFTHash(
+        assetId,
+        leafOwner,
+        leafDelegate,
+        nonce,
+        metadata
+      );
+      const tree = MerkleTree.sparseMerkleTreeFromLeaves(
+        [leaf],
+        depthSize.maxDepth
+      );
+      return {
+        root: [...tree.root],
+        proof: tree
+          .getProof(0)
+          .proof.map((node) => new web3.PublicKey(node)),
+      };
+    };
+
+    // List it, the leaf moving to the program custody
+    const collectionPda = await getCollectionPda(key);
+    const custody = await getCNFTCustodyPda();
+    const nft = {
+      rentalEnabled: true,
+      rentalPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      rentalMaxDays: 3,
+      paymentMint: new web3.PublicKey(0),
+    } as NFT;
+    let { root, proof } = leafProof(owner.publicKey, owner.publicKey);
+    await createCNFT(
+      assetId,
+      merkleTree.publicKey,
+      {
+        root,
+        dataHash: [...computeDataHash(metadata)],
+        creatorHash: [...computeCreatorHash(metadata.creators)],
+        nonce,
+        index: 0,
+      },
+      proof,
+      collectionPda.pda,
+      nft,
+      owner.publicKey,
+      [owner]
+    );
+    const cnft = await fetchCNFT(assetId);
+    assert.equal(cnft.merkleTree.toBase58(), merkleTree.publicKey.toBase58());
+    let fetched = await fetchNFT(assetId);
+    assert.equal(fetched.owner.toBase58(), owner.publicKey.toBase58());
+
+    // The renter is recorded on the nft only
+    ({ root, proof } = leafProof(custody.pda, custody.pda));
+    await rentCNFT(assetId, 1, renter.publicKey, [renter]);
+    fetched = await fetchNFT(assetId);
+    assert.equal(fetched.renter.toBase58(), renter.publicKey.toBase58());
+    // The custody is still the leaf owner & delegate
+    const treeAccount = await ConcurrentMerkleTreeAccount.fromAccountAddress(
+      connection,
+      merkleTree.publicKey
+    );
+    assert.deepEqual([...treeAccount.getCurrentRoot()], root);
+
+    // Neither released nor delisted before its term
+    try {
+      await releaseCNFT(assetId);
+      assert.fail("Running cnft rental released");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftRented");
+    }
+    try {
+      await deleteCNFT(assetId, root, proof, [owner]);
+      assert.fail("Rented cnft delisted");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "NftRented");
+    }
+  });
+
+  it("mints and lists nft", async () => {
+    const collection = await fetchCollection(key);
+    await createCollectionMint(key, "https://some_collection.json");
+    // Default terms outside of the policy bounds are rejected
+    try {
+      await updateCollectionPolicy(key, {
+        ...collection.policy,
+        defaultPrice: new anchor.BN(LAMPORTS_PER_SOL),
+        defaultMaxDays: 5,
+        maxPrice: new anchor.BN(LAMPORTS_PER_SOL / 2),
+      });
+      assert.fail("Default price above the collection max price accepted");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "InvalidRentalPolicy");
+    }
+    await updateCollectionPolicy(key, {
+      ...collection.policy,
+      defaultPrice: new anchor.BN(LAMPORTS_PER_SOL / 10),
+      defaultMaxDays: 5,
+    });
+    const mint = await mintAndList(key, "https://some_nft.json", "Minted");
+    const nft = await fetchNFT(mint);
+    assert.ok(nft.rentalEnabled);
+    assert.equal(nft.rentalPrice.toNumber(), LAMPORTS_PER_SOL / 10);
+    assert.equal(nft.rentalMaxDays, 5);
+    assert.equal(nft.owner.toBase58(), key.authority.toBase58());
+    await updateCollectionPolicy(key, collection.policy);
+  });
+
+  it("syncs rented metadata", async () => {
+    const collection = await fetchCollection(key);
+    await setRentedMetadata(key, true);
+    await updateCollectionPolicy(key, {
+      ...collection.policy,
+      defaultPrice: new anchor.BN(1000),
+      defaultMaxDays: 5,
+    });
+    const mints = [];
+    for (let k = 0; k < 3; k++) {
+      mints.push(await mintAndList(key, "https://some_nft.json", "Minted"));
+    }
+    const [bundled, offered, auctioned] = await Promise.all(
+      mints.map((mint) => fetchNFT(mint))
+    );
+    // Bundles
+    await rentBundle([bundled], 1, renter.publicKey, [renter]);
+    // Offers
+    await createRentalOffer(
+      offered.mint,
+      new anchor.BN(1000),
+      1,
+      Math.floor(Date.now() / 1e3) + DAY_S,
+      renter.publicKey,
+      [renter]
+    );
+    await acceptRentalOffer(offered, renter.publicKey);
+    // Auctions
+    const biddingEnd = Math.floor(Date.now() / 1e3) + 5;
+    await startRentalAuction(auctioned, 1, new anchor.BN(1000), biddingEnd);
+    await placeBid(auctioned.mint, new anchor.BN(2000), bidder.publicKey, [
+      bidder,
+    ]);
+    await new Promise((resolve) => setTimeout(resolve, 8000));
+    await settleRentalAuction(auctioned);
+    for (const mint of mints) {
+      const metadata = await Metadata.fromAccountAddress(
+        connection,
+        await getMetadataPda(mint)
+      );
+      assert.ok(metadata.data.uri.includes("rented=1"));
+    }
+    await setRentedMetadata(key, false);
+    await updateCollectionPolicy(key, collection.policy);
+  });
+
+  it("can't close collection with listings", async () => {
+    try {
+      await closeCollection(key);
+      assert.fail("Collection closed with listings");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "CollectionHasListings");
+      assert.equal(e.error.errorCode.number, 6201);
+    }
+  });
+
+  it("verifies the program creator for collection mints only", async () => {
+    try {
+      await mint(
+        "https://www.arweave.net/bxIAEavpOJeRyOKJFbMFjCZ4To6PNVefyj5epo40OCA/",
+        "CryptoPet #2642",
+        "CP",
+        {
+          creators: [
+            { address: await getCreatorAuthorityPubkey(), share: 100 },
+          ],
+        }
+      );
+      assert.fail("Program creator verified outside of a collection");
+    } catch (e) {
+      assert.equal(e.error.errorCode.code, "InvalidCreator");
     }
   });
 